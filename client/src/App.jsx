@@ -6,7 +6,15 @@ import CubeMovesToolbar from './CubeMovesToolbar';
 
 export default function App() {
     const [faces, setFaces] = useState([]);
+    // how each sticker is turned, only kept for a supercube where the centers show it
+    const [orientations, setOrientations] = useState([]);
     const [pieces, setPieces] = useState([]);
+    const [solved, setSolved] = useState(false);
+    const [scramble, setScramble] = useState('');
+    const [solveSteps, setSolveSteps] = useState([]);
+    const [scheme, setScheme] = useState('western');
+    const [supercube, setSupercube] = useState(false);
+    const [easing, setEasing] = useState('linear');
 
     // Function to fetch the cube state
     const fetchCubeState = async () => {
@@ -14,36 +22,32 @@ export default function App() {
             const response = await fetch('http://localhost:8000/get_cube');
             const data = await response.json();
             setFaces(data.faces);
+            setOrientations(data.supercube ? data.orientations : []);
+            setSolved(data.solved);
             setPieces(data.pieces);
         } catch (error) {
             console.error('Error fetching cube data:', error);
         }
     };
-    const applyAnimation = (index, animation, pieces) => {
-        setTimeout(() => {
-            if (index < animation.length) {
-                const animationPieces = animation[index];
-                setPieces(animationPieces);
-                applyAnimation(index + 1, animation, pieces);
-            } else {
-                setPieces(pieces);
-            }
-        }, 50);
+    // Function to play the frames of a cube state at the times the server gives, then show the pieces it ended with
+    const playAnimation = (cube) => {
+        cube.animation.forEach((frame, index) => {
+            setTimeout(() => setPieces(frame), cube.animation_times_ms[index]);
+        });
+        setTimeout(() => setPieces(cube.pieces), cube.animation_duration_ms);
     };
 
     // Function to apply a move
     const applyMove = async (move) => {
         try {
-            const response = await fetch(`http://localhost:8000/apply_move/${move}`, {
+            const response = await fetch(`http://localhost:8000/apply_move/${move}?easing=${easing}`, {
                 method: 'POST',
             });
             const data = await response.json();
             setFaces(data.faces);
-            if (data.animation && data.animation.length) {
-                applyAnimation(0, data.animation, data.pieces);
-            } else {
-                setPieces(data.pieces);
-            }
+            setOrientations(data.supercube ? data.orientations : []);
+            setSolved(data.solved);
+            playAnimation(data);
         } catch (error) {
             console.error('Error applying move:', error);
         }
@@ -53,6 +57,60 @@ export default function App() {
         applyMove(move);
     };
 
+    // Function to start over with a new solved cube of the given size, in the chosen color scheme and as a supercube if chosen
+    const handleNewCube = async (size) => {
+        try {
+            const response = await fetch(`http://localhost:8000/new_cube?size=${size}&scheme=${encodeURIComponent(scheme)}&supercube=${supercube}`, {
+                method: 'POST',
+            });
+            const data = await response.json();
+            setFaces(data.faces);
+            setOrientations(data.supercube ? data.orientations : []);
+            setSolved(data.solved);
+            setPieces(data.pieces);
+        } catch (error) {
+            console.error('Error creating cube:', error);
+        }
+    };
+
+    // Function to scramble the cube into a random state
+    const handleScramble = async () => {
+        try {
+            const response = await fetch(`http://localhost:8000/scramble?apply=true&easing=${easing}`, {
+                method: 'POST',
+            });
+            const data = await response.json();
+            setScramble(data.scramble);
+            setFaces(data.cube.faces);
+            setOrientations(data.cube.supercube ? data.cube.orientations : []);
+            setSolved(data.cube.solved);
+            playAnimation(data.cube);
+        } catch (error) {
+            console.error('Error scrambling cube:', error);
+        }
+    };
+
+    // Function to solve the cube layer by layer and show the steps
+    const handleSolve = async () => {
+        try {
+            const response = await fetch(`http://localhost:8000/solve?method=beginner&easing=${easing}`, {
+                method: 'POST',
+            });
+            if (!response.ok) {
+                console.error('Error solving cube:', await response.text());
+                return;
+            }
+            const data = await response.json();
+            setSolveSteps(data.steps);
+            setFaces(data.cube.faces);
+            setOrientations(data.cube.supercube ? data.cube.orientations : []);
+            setSolved(data.cube.solved);
+            playAnimation(data.cube);
+        } catch (error) {
+            console.error('Error solving cube:', error);
+        }
+    };
+
     useEffect(() => {
         fetchCubeState();
     }, []);
@@ -61,10 +119,25 @@ export default function App() {
         <div className="flex items-center justify-center min-h-screen">
             <div className="container mx-auto">
                 <div className="grid grid-cols-2 gap-4 mb-4">
-                    <CubeDisplay faces={faces} />
+                    <CubeDisplay faces={faces} orientations={orientations} />
                     <CubeDisplay3D pieces={pieces} />
                 </div>
-                <CubeMovesToolbar handleApplyMove={handleApplyMove} />
+                {solved && <p className="text-center text-green-600 font-bold mb-4">Solved!</p>}
+                {scramble && <p className="text-center font-mono mb-4">{scramble}</p>}
+                {solveSteps.length > 0 && (
+                    <ol className="mb-4">
+                        {solveSteps.map((step) => (
+                            <li key={step.name} className="mb-2">
+                                <span className="font-bold">{step.name}</span>: {step.explanation}
+                                <p className="font-mono">{step.moves}</p>
+                                <p className="text-sm text-gray-600">
+                                    {step.counts.htm} HTM, {step.counts.qtm} QTM, {step.counts.stm} STM, {step.counts.etm} ETM
+                                </p>
+                            </li>
+                        ))}
+                    </ol>
+                )}
+                <CubeMovesToolbar handleApplyMove={handleApplyMove} handleNewCube={handleNewCube} handleScramble={handleScramble} handleSolve={handleSolve} scheme={scheme} setScheme={setScheme} supercube={supercube} setSupercube={setSupercube} easing={easing} setEasing={setEasing} />
             </div>
         </div>
     );
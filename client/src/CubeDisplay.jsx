@@ -1,32 +1,32 @@
 import { useState } from 'react';
 import Face from './Face';
 
-export default function CubeDisplay({ faces }) {
+export default function CubeDisplay({ faces, orientations = [] }) {
     if (faces.length) {
         return (
             <>
                 <div className="grid grid-cols-4 w-full gap-4">
                     <div></div>
                     <div>
-                        <Face key="Up" data={faces[0]} />
+                        <Face key="Up" data={faces[0]} orientations={orientations[0]} />
                     </div>
                     <div></div>
                     <div></div>
                     <div>
-                        <Face key="Left" data={faces[1]} />
+                        <Face key="Left" data={faces[1]} orientations={orientations[1]} />
                     </div>
                     <div>
-                        <Face key="Front" data={faces[2]} />
+                        <Face key="Front" data={faces[2]} orientations={orientations[2]} />
                     </div>
                     <div>
-                        <Face key="Right" data={faces[3]} />
+                        <Face key="Right" data={faces[3]} orientations={orientations[3]} />
                     </div>
                     <div>
-                        <Face key="Back" data={faces[4]} />
+                        <Face key="Back" data={faces[4]} orientations={orientations[4]} />
                     </div>
                     <div></div>
                     <div>
-                        <Face key="Down" data={faces[5]} />
+                        <Face key="Down" data={faces[5]} orientations={orientations[5]} />
                     </div>
                     <div></div>
                     <div></div>
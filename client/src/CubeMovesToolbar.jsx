@@ -1,9 +1,43 @@
-export default function CubeMovesToolbar({ handleApplyMove }) {
-    const moves = ['U', 'UPrime', 'U2', 'D', 'DPrime', 'D2', 'L', 'LPrime', 'L2', 'R', 'RPrime', 'R2', 'F', 'FPrime', 'F2', 'B', 'BPrime', 'B2'];
+export default function CubeMovesToolbar({ handleApplyMove, handleNewCube, handleScramble, handleSolve, scheme, setScheme, supercube, setSupercube, easing, setEasing }) {
+    const sizes = [2, 3, 4, 5, 6, 7];
+    const schemes = ['western', 'japanese'];
+    const easings = ['linear', 'ease-in-out', 'cubic'];
+    const moves = ['U', 'UPrime', 'U2', 'D', 'DPrime', 'D2', 'L', 'LPrime', 'L2', 'R', 'RPrime', 'R2', 'F', 'FPrime', 'F2', 'B', 'BPrime', 'B2', 'M', 'MPrime', 'M2', 'E', 'EPrime', 'E2', 'S', 'SPrime', 'S2', 'x', 'xPrime', 'x2', 'y', 'yPrime', 'y2', 'z', 'zPrime', 'z2', 'Uw', 'UwPrime', 'Uw2', 'Dw', 'DwPrime', 'Dw2', 'Lw', 'LwPrime', 'Lw2', 'Rw', 'RwPrime', 'Rw2', 'Fw', 'FwPrime', 'Fw2', 'Bw', 'BwPrime', 'Bw2'];
 
     return (
         <div className="p-4">
             <h1 className="text-2xl font-bold mb-4">Rubik's Cube Moves</h1>
+            <div className="flex gap-4 mb-4">
+                <select value={scheme} onChange={(event) => setScheme(event.target.value)} className="border rounded px-2">
+                    {schemes.map((name) => (
+                        <option key={name} value={name}>
+                            {name}
+                        </option>
+                    ))}
+                </select>
+                <select value={easing} onChange={(event) => setEasing(event.target.value)} className="border rounded px-2">
+                    {easings.map((name) => (
+                        <option key={name} value={name}>
+                            {name}
+                        </option>
+                    ))}
+                </select>
+                <label className="flex items-center gap-1">
+                    <input type="checkbox" checked={supercube} onChange={(event) => setSupercube(event.target.checked)} />
+                    supercube
+                </label>
+                {sizes.map((size) => (
+                    <button onClick={() => handleNewCube(size)} key={size} className="bg-slate-500 text-white py-2 px-4 rounded hover:bg-slate-700">
+                        {size}x{size}
+                    </button>
+                ))}
+                <button onClick={handleScramble} className="bg-slate-500 text-white py-2 px-4 rounded hover:bg-slate-700">
+                    Scramble
+                </button>
+                <button onClick={handleSolve} className="bg-slate-500 text-white py-2 px-4 rounded hover:bg-slate-700">
+                    Solve
+                </button>
+            </div>
             <div className="grid grid-cols-3 gap-4">
                 {moves.map((move, index) => (
                     <button onClick={() => handleApplyMove(move)} key={index} className="bg-blue-500 text-white py-2 px-4 rounded hover:bg-blue-700">
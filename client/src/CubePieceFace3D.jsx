@@ -18,6 +18,7 @@ const sideToRotation = {
 
 import React, { useRef, useEffect } from 'react';
 import * as THREE from 'three';
+import { stickerColorToCss } from './colors';
 
 export default function CubePieceFace3D({ side, position, rotation, color, ...props }) {
     const planeRef = useRef();
@@ -88,7 +89,7 @@ export default function CubePieceFace3D({ side, position, rotation, color, ...pr
     return (
         <mesh ref={planeRef} position={[...sideToVectorMap[side]]} {...props}>
             <planeGeometry args={[0.9, 0.9]} />
-            <meshStandardMaterial color={color} side={THREE.DoubleSide} />
+            <meshStandardMaterial color={stickerColorToCss(color)} side={THREE.DoubleSide} />
         </mesh>
     );
 }
@@ -1,3 +1,5 @@
+import { stickerColorToCss } from './colors';
+
 const colorClassMap = {
     Red: 'bg-red-500',
     Blue: 'bg-blue-500',
@@ -7,15 +9,28 @@ const colorClassMap = {
     Orange: 'bg-orange-500',
 };
 
-export default function Face({ data }) {
+// orientations, when given, mark the center with a bar along the edge that started at its top
+export default function Face({ data, orientations }) {
+    const middle = (data.length - 1) / 2;
     return (
         <div className="space-y-1">
             {data.map((row, ri) => {
                 return (
                     <div key={`row-${ri}`} className="flex space-x-1">
                         {row.map((col, ci) => {
-                            const bgColorClass = colorClassMap[col];
-                            return <div key={`${ri}-${ci}-${col}`} className={`${bgColorClass} h-8 w-8 rounded border-2 border-slate-500`}></div>;
+                            // named colors use the theme, custom ones from the color scheme are set directly
+                            const bgColorClass = colorClassMap[col] ?? '';
+                            const style = colorClassMap[col] ? undefined : { backgroundColor: stickerColorToCss(col) };
+                            const isCenter = orientations && ri === middle && ci === middle;
+                            return (
+                                <div key={`${ri}-${ci}`} className={`${bgColorClass} h-8 w-8 rounded border-2 border-slate-500`} style={style}>
+                                    {isCenter && (
+                                        <div className="h-full w-full p-0.5" style={{ transform: `rotate(${orientations[ri][ci] * 90}deg)` }}>
+                                            <div className="h-1 w-full rounded bg-slate-700"></div>
+                                        </div>
+                                    )}
+                                </div>
+                            );
                         })}
                     </div>
                 );
@@ -0,0 +1,8 @@
+// CSS color of a sticker color from the server, either a name like "White" or { Rgb: [red, green, blue] }
+export function stickerColorToCss(color) {
+    if (typeof color === 'string') {
+        return color.toLowerCase();
+    }
+    const [red, green, blue] = color.Rgb;
+    return `rgb(${red}, ${green}, ${blue})`;
+}
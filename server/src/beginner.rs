@@ -0,0 +1,406 @@
+use crate::cube::{rotate, simplify, Cube, CubeMove, Direction};
+use crate::cubie::{Corner, CubieCube, Edge, FACE_TURNS};
+use crate::notation;
+use crate::two_phase::SolveError;
+use crate::validation;
+use std::collections::VecDeque;
+use std::sync::OnceLock;
+
+// Layer by layer solver for a 3x3, the way most people learn to solve the cube
+// The first layer is solved on the bottom and the last layer on top. Apart from the cross, every step
+// only turns the top layer to set up and then plays one of a few algorithms, found by a short search
+// over those choices, so the solution reads like a person following the method.
+
+// A named part of a solution, with what it does in a sentence
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolveStep {
+    pub name: &'static str,
+    pub explanation: &'static str,
+    pub moves: Vec<CubeMove>,
+}
+
+// a sequence played as one choice in a search, like an algorithm or a top layer turn
+struct Macro {
+    moves: Vec<CubeMove>,
+    cubie: CubieCube,
+    is_top_turn: bool,
+}
+
+impl Macro {
+    fn new(moves: Vec<CubeMove>) -> Self {
+        Macro {
+            cubie: CubieCube::from_moves(&moves).unwrap(),
+            is_top_turn: moves.iter().all(|m| m.axis() == CubeMove::U.axis()),
+            moves,
+        }
+    }
+
+    fn from_notation(algorithm: &str) -> Self {
+        Macro::new(notation::parse(algorithm).unwrap())
+    }
+
+    // the algorithm played from each of the four sides, from the front first and then going around to the right
+    fn from_each_side(algorithm: &str) -> Vec<Self> {
+        let moves = notation::parse(algorithm).unwrap();
+        [
+            None,
+            Some(CubeMove::Y),
+            Some(CubeMove::Y2),
+            Some(CubeMove::YPrime),
+        ]
+        .iter()
+        .map(|rotation| match rotation {
+            Some(rotation) => Macro::new(rotate(&moves, rotation)),
+            None => Macro::new(moves.clone()),
+        })
+        .collect()
+    }
+
+    fn top_turns() -> Vec<Self> {
+        [CubeMove::U, CubeMove::U2, CubeMove::UPrime]
+            .into_iter()
+            .map(|m| Macro::new(vec![m]))
+            .collect()
+    }
+}
+
+const CROSS_EDGES: [Edge; 4] = [Edge::DR, Edge::DF, Edge::DL, Edge::DB];
+const FIRST_LAYER_CORNERS: [Corner; 4] = [Corner::DFR, Corner::DLF, Corner::DBL, Corner::DRB];
+const MIDDLE_LAYER_EDGES: [Edge; 4] = [Edge::FR, Edge::FL, Edge::BL, Edge::BR];
+const TOP_LAYER: [usize; 4] = [0, 1, 2, 3]; // corners URF to UBR and edges UR to UB
+
+fn cross_solved(cubie: &CubieCube) -> bool {
+    CROSS_EDGES.iter().all(|&edge| cubie.edge_solved(edge))
+}
+
+// where the cross edges are and how they are flipped, 24 choices for each of the four
+fn cross_index(cubie: &CubieCube) -> usize {
+    CROSS_EDGES.iter().fold(0, |index, edge| {
+        let slot = cubie.ep.iter().position(|e| e == edge).unwrap();
+        index * 24 + slot * 2 + cubie.eo[slot] as usize
+    })
+}
+
+// distance to a solved cross for every placement of the cross edges, there are few enough to try them all
+fn cross_distances() -> &'static Vec<u8> {
+    static DISTANCES: OnceLock<Vec<u8>> = OnceLock::new();
+    DISTANCES.get_or_init(|| {
+        let moves: Vec<CubieCube> = FACE_TURNS
+            .iter()
+            .map(|m| CubieCube::from_move(m).unwrap())
+            .collect();
+        let solved = CubieCube::identity();
+        let mut distances = vec![u8::MAX; 24usize.pow(4)];
+        distances[cross_index(&solved)] = 0;
+        // only the cross edges matter, so the rest of each cube in the queue is whatever the turns made it
+        let mut queue = VecDeque::from([solved]);
+        while let Some(cubie) = queue.pop_front() {
+            let distance = distances[cross_index(&cubie)];
+            for m in moves.iter() {
+                let next = cubie.compose(m);
+                let index = cross_index(&next);
+                if distances[index] == u8::MAX {
+                    distances[index] = distance + 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+        distances
+    })
+}
+
+// an optimal cross, always taking a move that gets one closer
+fn solve_cross(cubie: &mut CubieCube) -> Vec<CubeMove> {
+    let distances = cross_distances();
+    let mut moves = Vec::new();
+    while distances[cross_index(cubie)] > 0 {
+        let distance = distances[cross_index(cubie)];
+        let (next, m) = FACE_TURNS
+            .iter()
+            .map(|m| (cubie.compose(&CubieCube::from_move(m).unwrap()), m))
+            .find(|(next, _)| distances[cross_index(next)] < distance)
+            .unwrap();
+        *cubie = next;
+        moves.push(m.clone());
+    }
+    moves
+}
+
+// the fewest macros that reach the goal, top layer turns never follow each other
+fn search(
+    cubie: &CubieCube,
+    macros: &[Macro],
+    goal: &impl Fn(&CubieCube) -> bool,
+    max_depth: usize,
+) -> Option<Vec<CubeMove>> {
+    fn go(
+        cubie: &CubieCube,
+        macros: &[Macro],
+        goal: &impl Fn(&CubieCube) -> bool,
+        togo: usize,
+        path: &mut Vec<usize>,
+    ) -> bool {
+        if togo == 0 {
+            return goal(cubie);
+        }
+        for (index, m) in macros.iter().enumerate() {
+            if m.is_top_turn && path.last().is_some_and(|&last| macros[last].is_top_turn) {
+                continue;
+            }
+            path.push(index);
+            if go(&cubie.compose(&m.cubie), macros, goal, togo - 1, path) {
+                return true;
+            }
+            path.pop();
+        }
+        false
+    }
+
+    (0..=max_depth).find_map(|depth| {
+        let mut path = Vec::new();
+        go(cubie, macros, goal, depth, &mut path).then(|| {
+            path.iter()
+                .flat_map(|&index| macros[index].moves.clone())
+                .collect()
+        })
+    })
+}
+
+// solve the pieces in the slots one at a time, each time picking whichever piece takes the fewest macros
+// pieces solved earlier, and everything keep checks for, have to stay solved
+fn solve_pieces<Piece: Copy + PartialEq>(
+    cubie: &mut CubieCube,
+    macros: &[Macro],
+    slots: &[Piece],
+    is_solved: impl Fn(&CubieCube, Piece) -> bool,
+    keep: impl Fn(&CubieCube) -> bool,
+    max_depth: usize,
+) -> Option<Vec<CubeMove>> {
+    let mut moves = Vec::new();
+    loop {
+        let solved: Vec<Piece> = slots
+            .iter()
+            .copied()
+            .filter(|&slot| is_solved(cubie, slot))
+            .collect();
+        if solved.len() == slots.len() {
+            return Some(moves);
+        }
+        let goal = |next: &CubieCube| {
+            keep(next)
+                && solved.iter().all(|&slot| is_solved(next, slot))
+                && slots
+                    .iter()
+                    .any(|&slot| !solved.contains(&slot) && is_solved(next, slot))
+        };
+        let found = search(cubie, macros, &goal, max_depth)?;
+        *cubie = cubie.compose(&CubieCube::from_moves(&found).unwrap());
+        moves.extend(found);
+    }
+}
+
+// play macros until the goal is reached, keeping the first two layers solved
+fn solve_last_layer(
+    cubie: &mut CubieCube,
+    macros: &[Macro],
+    goal: impl Fn(&CubieCube) -> bool,
+    max_depth: usize,
+) -> Option<Vec<CubeMove>> {
+    let goal = |next: &CubieCube| next.first_two_layers_solved(&Direction::Up) && goal(next);
+    let moves = search(cubie, macros, &goal, max_depth)?;
+    *cubie = cubie.compose(&CubieCube::from_moves(&moves).unwrap());
+    Some(moves)
+}
+
+// join top layer turns that ended up next to each other, like the U' ending an insert and a U2 setup after it
+// everything else is left alone so the algorithms stay recognizable
+fn merge_top_turns(moves: Vec<CubeMove>) -> Vec<CubeMove> {
+    let top = CubeMove::U.layer_group();
+    let mut merged: Vec<CubeMove> = Vec::new();
+    for cube_move in moves {
+        match merged.last() {
+            Some(last) if cube_move.layer_group() == top && last.layer_group() == top => {
+                let last = merged.pop().unwrap();
+                merged.extend(simplify(&[last, cube_move]));
+            }
+            _ => merged.push(cube_move),
+        }
+    }
+    merged
+}
+
+// solve a 3x3 layer by layer, in the steps of the beginner method
+// works from the centers, so the cube can be held any way and the steps solve it as it is held
+pub fn solve(cube: &Cube) -> Result<Vec<SolveStep>, SolveError> {
+    validation::validate(cube).map_err(SolveError::Unsolvable)?;
+    let mut cubie = CubieCube::relative_to_centers(cube).map_err(SolveError::UnsupportedCube)?;
+
+    let top_turns = || Macro::top_turns().into_iter();
+    let corner_inserts: Vec<Macro> = top_turns()
+        .chain(
+            Macro::from_each_side("R U R' U'")
+                .into_iter()
+                .flat_map(|insert| {
+                    // repeating the insert up to five times twists the corner into place
+                    (1..=5).map(move |times| Macro::new(vec![insert.moves.clone(); times].concat()))
+                }),
+        )
+        .collect();
+    let edge_inserts: Vec<Macro> = top_turns()
+        .chain(Macro::from_each_side("U R U' R' U' F' U F"))
+        .chain(Macro::from_each_side("U' L' U L U F U' F'"))
+        .collect();
+    let edge_flips: Vec<Macro> = top_turns()
+        .chain([Macro::from_notation("F R U R' U' F'")])
+        .collect();
+    let corner_twists: Vec<Macro> = top_turns()
+        .chain([Macro::from_notation("R U R' U R U2 R'")])
+        .collect();
+    let permutations: Vec<Macro> = top_turns()
+        .chain([Macro::from_notation("R' F R' B2 R F' R' B2 R2")])
+        .chain([Macro::from_notation("R U' R U R U R U' R' U' R2")])
+        .collect();
+
+    let corners_solved = |next: &CubieCube| {
+        FIRST_LAYER_CORNERS
+            .iter()
+            .all(|&corner| next.corner_solved(corner))
+    };
+    let steps = vec![
+        SolveStep {
+            name: "Cross",
+            explanation: "Solve the four bottom edges, each matching the bottom center and the center next to it.",
+            moves: solve_cross(&mut cubie),
+        },
+        SolveStep {
+            name: "First layer corners",
+            explanation: "Turn the top until a bottom corner is above its slot, then repeat R U R' U' until it drops in solved.",
+            moves: solve_pieces(
+                &mut cubie,
+                &corner_inserts,
+                &FIRST_LAYER_CORNERS,
+                CubieCube::corner_solved,
+                cross_solved,
+                3,
+            )
+            .ok_or(SolveError::NotFound)?,
+        },
+        SolveStep {
+            name: "Second layer",
+            explanation: "Line a middle edge up with its center on the front, then insert it to the right with U R U' R' U' F' U F or to the left with U' L' U L U F U' F'.",
+            moves: solve_pieces(
+                &mut cubie,
+                &edge_inserts,
+                &MIDDLE_LAYER_EDGES,
+                CubieCube::edge_solved,
+                |next| cross_solved(next) && corners_solved(next),
+                3,
+            )
+            .ok_or(SolveError::NotFound)?,
+        },
+        SolveStep {
+            name: "Last layer cross",
+            explanation: "Play F R U R' U' F' to go from a dot to an L, from the L to a line and from the line to a cross on top.",
+            moves: solve_last_layer(
+                &mut cubie,
+                &edge_flips,
+                |next| TOP_LAYER.iter().all(|&slot| next.eo[slot] == 0),
+                8,
+            )
+            .ok_or(SolveError::NotFound)?,
+        },
+        SolveStep {
+            name: "OLL",
+            explanation: "Orient the last layer: play R U R' U R U2 R' and turn the top until the whole top is one color.",
+            moves: solve_last_layer(
+                &mut cubie,
+                &corner_twists,
+                |next| TOP_LAYER.iter().all(|&slot| next.eo[slot] == 0 && next.co[slot] == 0),
+                10,
+            )
+            .ok_or(SolveError::NotFound)?,
+        },
+        SolveStep {
+            name: "PLL",
+            explanation: "Permute the last layer: swap corners with R' F R' B2 R F' R' B2 R2, cycle edges with R U' R U R U R U' R' U' R2, then turn the top into place.",
+            moves: solve_last_layer(&mut cubie, &permutations, |next| next.is_identity(), 10)
+                .ok_or(SolveError::NotFound)?,
+        },
+    ];
+
+    Ok(steps
+        .into_iter()
+        .map(|step| SolveStep {
+            moves: merge_top_turns(step.moves),
+            ..step
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scramble::{random_move_scramble, rng_from_seed, MoveSet};
+    use crate::validation::ValidationError;
+
+    fn solved_by_steps(cube: &mut Cube) -> Vec<&'static str> {
+        let steps = solve(cube).unwrap();
+        for step in steps.iter() {
+            for cube_move in step.moves.iter() {
+                cube.apply_move(cube_move);
+            }
+        }
+        steps.iter().map(|step| step.name).collect()
+    }
+
+    #[test]
+    fn solves_scrambles_in_named_steps() {
+        let mut rng = rng_from_seed(1);
+        for _ in 0..10 {
+            let mut cube = Cube::new();
+            for cube_move in random_move_scramble(&mut rng, 25, &MoveSet::face_turns()) {
+                cube.apply_move(&cube_move);
+            }
+            let names = solved_by_steps(&mut cube);
+            assert!(cube.is_solved());
+            assert_eq!(
+                names,
+                [
+                    "Cross",
+                    "First layer corners",
+                    "Second layer",
+                    "Last layer cross",
+                    "OLL",
+                    "PLL"
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn solves_a_cube_held_another_way() {
+        let mut cube = Cube::new();
+        for cube_move in notation::parse("R U F' L2 D x y' M E'").unwrap() {
+            cube.apply_move(&cube_move);
+        }
+        solved_by_steps(&mut cube);
+        assert!(cube.is_solved_up_to_rotation());
+    }
+
+    #[test]
+    fn rejects_cubes_it_cant_solve() {
+        // the UF edge flipped in place
+        let flipped =
+            Cube::from_facelets("UUUUUUUFURRRRRRRRRFUFFFFFFFDDDDDDDDDLLLLLLLLLBBBBBBBBB").unwrap();
+        assert_eq!(
+            solve(&flipped),
+            Err(SolveError::Unsolvable(vec![ValidationError::EdgeFlipped {
+                edge: Edge::UF
+            }]))
+        );
+        assert!(matches!(
+            solve(&Cube::with_size(4)),
+            Err(SolveError::UnsupportedCube(_))
+        ));
+    }
+}
@@ -0,0 +1,140 @@
+use crate::cube::{Cube, Direction, StickerColor};
+use crate::cubie::{Corner, CubieCube, CubieError, Edge, FACE_TURNS};
+use serde::Serialize;
+use strum::IntoEnumIterator;
+
+// How far a 3x3 is through CFOP: cross, first two layers (F2L), orientation of the last layer (OLL),
+// permutation of the last layer (PLL) and the final turn of the last layer (AUF)
+// Every color is looked at as the cross color, since solvers pick different ones. The cube is read relative to
+// its centers, so a piece is solved when it sits in its own slot untwisted, however the cube is held.
+
+// The steps of CFOP in the order they are done
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum CfopStep {
+    Cross,
+    F2L,
+    OLL,
+    PLL,
+    AUF,
+    Solved,
+}
+
+// A corner and the middle layer edge next to it, named by the colors of the edge
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct F2lPair {
+    pub colors: [StickerColor; 2],
+    pub inserted: bool,
+}
+
+// Progress with the cross on one color
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CrossProgress {
+    pub color: StickerColor,
+    pub cross_edges: usize, // how many of the 4 cross edges are solved
+    pub pairs: Vec<F2lPair>,
+    pub oll_solved: bool, // the first two layers are solved and the last layer shows a single color on top
+    pub next_step: CfopStep,
+}
+
+// Progress for every cross color, furthest along first
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CfopAnalysis {
+    pub crosses: Vec<CrossProgress>,
+}
+
+// whether the piece in every slot on the face shows the color of the face there
+// a piece moves its k-th face to the slot face twist (or flip) steps further along, see From<&CubieCube> for Cube
+pub(crate) fn face_oriented(cubie: &CubieCube, face: &Direction) -> bool {
+    let corners_oriented = Corner::iter()
+        .filter(|slot| slot.faces().contains(face))
+        .all(|slot| {
+            let piece_faces = cubie.cp[slot as usize].faces();
+            piece_faces
+                .iter()
+                .position(|f| f == face)
+                .is_some_and(|k| slot.faces()[(k + cubie.co[slot as usize] as usize) % 3] == *face)
+        });
+    let edges_oriented = Edge::iter()
+        .filter(|slot| slot.faces().contains(face))
+        .all(|slot| {
+            let piece_faces = cubie.ep[slot as usize].faces();
+            piece_faces
+                .iter()
+                .position(|f| f == face)
+                .is_some_and(|k| slot.faces()[(k + cubie.eo[slot as usize] as usize) % 2] == *face)
+        });
+    corners_oriented && edges_oriented
+}
+
+fn cross_progress(cube: &Cube, cubie: &CubieCube, face: &Direction) -> CrossProgress {
+    let center_sides = cube.center_sides();
+    let last_layer = face.opposite();
+
+    let cross_edges = Edge::iter()
+        .filter(|edge| edge.faces().contains(face))
+        .filter(|&edge| cubie.edge_solved(edge))
+        .count();
+    let pairs: Vec<F2lPair> = Corner::iter()
+        .filter(|corner| corner.faces().contains(face))
+        .map(|corner| {
+            let sides: Vec<Direction> = corner
+                .faces()
+                .into_iter()
+                .filter(|side| side != face)
+                .collect();
+            let edge = Edge::iter()
+                .find(|edge| sides.iter().all(|side| edge.faces().contains(side)))
+                .unwrap();
+            F2lPair {
+                colors: [
+                    center_sides.color_on(&sides[0]),
+                    center_sides.color_on(&sides[1]),
+                ],
+                inserted: cubie.corner_solved(corner) && cubie.edge_solved(edge),
+            }
+        })
+        .collect();
+    let f2l_solved = cross_edges == 4 && pairs.iter().all(|pair| pair.inserted);
+    let oll_solved = f2l_solved && face_oriented(cubie, &last_layer);
+
+    let next_step = if cross_edges < 4 {
+        CfopStep::Cross
+    } else if !f2l_solved {
+        CfopStep::F2L
+    } else if !oll_solved {
+        CfopStep::OLL
+    } else if cubie.is_identity() {
+        CfopStep::Solved
+    } else if FACE_TURNS
+        .iter()
+        .filter(|face_turn| face_turn.to_string().starts_with(last_layer.letter()))
+        .filter_map(CubieCube::from_move)
+        .any(|turn| cubie.compose(&turn).is_identity())
+    {
+        CfopStep::AUF
+    } else {
+        CfopStep::PLL
+    };
+
+    CrossProgress {
+        color: center_sides.color_on(face),
+        cross_edges,
+        pairs,
+        oll_solved,
+        next_step,
+    }
+}
+
+// how far a 3x3 is through CFOP for each cross color
+pub fn analyze(cube: &Cube) -> Result<CfopAnalysis, CubieError> {
+    let cubie = CubieCube::relative_to_centers(cube)?;
+    let mut crosses: Vec<CrossProgress> = Direction::iter()
+        .map(|face| cross_progress(cube, &cubie, &face))
+        .collect();
+    crosses.sort_by_key(|progress| {
+        let inserted_pairs = progress.pairs.iter().filter(|pair| pair.inserted).count();
+        std::cmp::Reverse((progress.next_step, inserted_pairs, progress.cross_edges))
+    });
+    Ok(CfopAnalysis { crosses })
+}
@@ -1,5 +1,4 @@
 use rocket::http::Method;
-use rocket::response::Responder;
 use rocket::{
     fairing::{Fairing, Info, Kind},
     Request, Response,
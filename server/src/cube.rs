@@ -1,28 +1,82 @@
-use nalgebra::{Rotation3, Unit, Vector2, Vector3};
+use crate::cubie::{Corner, Edge};
+use nalgebra::{Matrix3, Rotation3, Unit, Vector2, Vector3};
 use rocket::request::FromParam;
-use serde::{Deserialize, Serialize};
-use std::fmt::Debug;
+use rocket::FromFormField;
+use serde::Serialize;
+use std::fmt::{self, Debug};
+use std::ops::RangeInclusive;
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
-fn vector_is_composed_with_direction(v: Vector3<f32>, direction: &Direction) -> bool {
-    // for each element in vector, check if it has a direction (!= 0) and return true if it equals direction value
-    let direction_vector = direction.vector();
+// Positions are tracked exactly as integers in half units, so that the outer layer of a 3x3 sits at 2
+// and even sized cubes (whose pieces sit at 0.5, 1.5, ...) stay on whole numbers
+// extent is the distance from the center to the outer layer in those same half units
+
+fn vector_is_composed_with_direction(v: Vector3<i32>, direction: &Direction, extent: i32) -> bool {
+    // for each element in vector, check if it sits on the outside of the cube (== extent) in the same direction
+    let direction_vector = direction.grid_vector();
     for i in 0..3 {
-        if v[i] != 0.0 && v[i] == direction_vector[i] {
+        if v[i] != 0 && v[i] == direction_vector[i] * extent {
             return true;
         }
     }
     false
 }
 
-fn round_to_nearest_hundredth(vec: &Vector3<f32>) -> Vector3<f32> {
-    vec.map(|x| (x * 100.0).round() / 100.0)
+// check if a vector sits in one of the given layers, counted from the face in direction (0 being the outer layer)
+fn vector_is_in_layers(
+    v: Vector3<i32>,
+    direction: &Direction,
+    layers: &RangeInclusive<usize>,
+    extent: i32,
+) -> bool {
+    // neighbouring layers are two half units apart
+    let depth = (extent - v.dot(&direction.grid_vector())) / 2;
+    depth >= 0 && layers.contains(&(depth as usize))
+}
+
+// all 24 ways to rotate a cube in place, each as a signed permutation matrix, starting with the identity
+pub(crate) fn proper_rotations() -> Vec<Matrix3<i32>> {
+    let mut rotations = Vec::new();
+    for permutation in [
+        [0, 1, 2],
+        [0, 2, 1],
+        [1, 0, 2],
+        [1, 2, 0],
+        [2, 0, 1],
+        [2, 1, 0],
+    ] {
+        for signs in 0..8 {
+            let mut rotation = Matrix3::zeros();
+            for (column, row) in permutation.iter().enumerate() {
+                rotation[(*row, column)] = if signs & (1 << column) == 0 { 1 } else { -1 };
+            }
+            if rotation.cast::<f32>().determinant() > 0.0 {
+                rotations.push(rotation);
+            }
+        }
+    }
+    rotations
+}
+
+// exact rotation for a turn of a multiple of 90 degrees around a direction
+// the result is a signed permutation matrix, so rotating integer vectors with it never drifts
+fn quarter_turn_matrix(rotation_axis: &Direction, rotation_theta: f32) -> Matrix3<i32> {
+    let a = rotation_axis.grid_vector();
+    // cross product matrix of the axis, Rodrigues' formula with an exact sine and cosine
+    let k = Matrix3::new(0, -a.z, a.y, a.z, 0, -a.x, -a.y, a.x, 0);
+    let (sin, cos) = match ((rotation_theta / 90.0).round() as i32).rem_euclid(4) {
+        0 => (0, 1),
+        1 => (1, 0),
+        2 => (0, -1),
+        _ => (-1, 0),
+    };
+    Matrix3::identity() + k * sin + k * k * (1 - cos)
 }
 
 // 3D Direction enums for easier readability
 #[derive(PartialEq, Eq, Debug, EnumIter, Serialize, Clone)]
-enum Direction {
+pub(crate) enum Direction {
     Up,
     Down,
     Left,
@@ -44,41 +98,126 @@ impl Direction {
         }
     }
 
-    // convert vector to Direction
-    fn from_vector(vector: Vector3<f32>) -> Option<Direction> {
-        for direction in Direction::iter() {
-            if direction.vector() == Unit::new_normalize(vector) {
-                return Some(direction);
-            }
+    // integer unit vector of the direction, used by the exact piece model
+    pub(crate) fn grid_vector(&self) -> Vector3<i32> {
+        self.vector().into_inner().map(|x| x as i32)
+    }
+
+    // the face on the other side of the cube
+    pub(crate) fn opposite(&self) -> Direction {
+        Direction::from_grid_vector(-self.grid_vector()).unwrap()
+    }
+
+    // face letter used in notation and facelet strings
+    pub(crate) fn letter(&self) -> char {
+        match self {
+            Direction::Up => 'U',
+            Direction::Down => 'D',
+            Direction::Left => 'L',
+            Direction::Right => 'R',
+            Direction::Front => 'F',
+            Direction::Back => 'B',
         }
+    }
 
-        None
+    fn from_letter(letter: char) -> Option<Direction> {
+        Direction::iter().find(|direction| direction.letter() == letter)
+    }
+
+    // convert integer unit vector to Direction
+    fn from_grid_vector(vector: Vector3<i32>) -> Option<Direction> {
+        Direction::iter().find(|direction| direction.grid_vector() == vector)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum CubeMove {
-    U,      // Up
-    D,      // Down
-    L,      // Left
-    R,      // Right
-    F,      // Front
-    B,      // Back
-    UPrime, // Up counter-clockwise
-    DPrime, // Down counter-clockwise
-    LPrime, // Left counter-clockwise
-    RPrime, // Right counter-clockwise
-    FPrime, // Front counter-clockwise
-    BPrime, // Back counter-clockwise
-    U2,     // Up 180 degrees
-    D2,     // Down 180 degrees
-    L2,     // Left 180 degrees
-    R2,     // Right 180 degrees
-    F2,     // Front 180 degrees
-    B2,     // Back 180 degrees
-    Reset,  // Reset the cube
+    U,       // Up
+    D,       // Down
+    L,       // Left
+    R,       // Right
+    F,       // Front
+    B,       // Back
+    UPrime,  // Up counter-clockwise
+    DPrime,  // Down counter-clockwise
+    LPrime,  // Left counter-clockwise
+    RPrime,  // Right counter-clockwise
+    FPrime,  // Front counter-clockwise
+    BPrime,  // Back counter-clockwise
+    U2,      // Up 180 degrees
+    D2,      // Down 180 degrees
+    L2,      // Left 180 degrees
+    R2,      // Right 180 degrees
+    F2,      // Front 180 degrees
+    B2,      // Back 180 degrees
+    M,       // Middle slice, follows Left
+    E,       // Equator slice, follows Down
+    S,       // Standing slice, follows Front
+    MPrime,  // Middle slice counter-clockwise
+    EPrime,  // Equator slice counter-clockwise
+    SPrime,  // Standing slice counter-clockwise
+    M2,      // Middle slice 180 degrees
+    E2,      // Equator slice 180 degrees
+    S2,      // Standing slice 180 degrees
+    X,       // Whole cube rotation, follows Right
+    Y,       // Whole cube rotation, follows Up
+    Z,       // Whole cube rotation, follows Front
+    XPrime,  // Whole cube rotation counter-clockwise
+    YPrime,  // Whole cube rotation counter-clockwise
+    ZPrime,  // Whole cube rotation counter-clockwise
+    X2,      // Whole cube rotation 180 degrees
+    Y2,      // Whole cube rotation 180 degrees
+    Z2,      // Whole cube rotation 180 degrees
+    Uw,      // Wide Up, outer two layers
+    Dw,      // Wide Down, outer two layers
+    Lw,      // Wide Left, outer two layers
+    Rw,      // Wide Right, outer two layers
+    Fw,      // Wide Front, outer two layers
+    Bw,      // Wide Back, outer two layers
+    UwPrime, // Wide Up counter-clockwise
+    DwPrime, // Wide Down counter-clockwise
+    LwPrime, // Wide Left counter-clockwise
+    RwPrime, // Wide Right counter-clockwise
+    FwPrime, // Wide Front counter-clockwise
+    BwPrime, // Wide Back counter-clockwise
+    Uw2,     // Wide Up 180 degrees
+    Dw2,     // Wide Down 180 degrees
+    Lw2,     // Wide Left 180 degrees
+    Rw2,     // Wide Right 180 degrees
+    Fw2,     // Wide Front 180 degrees
+    Bw2,     // Wide Back 180 degrees
+    Reset,   // Reset the cube
+    // SiGN layer prefix on a face or wide turn: 2R turns only the second layer, 3Rw the outer three
+    Layered(usize, Box<CubeMove>),
 }
 
+// every family of moves that turn the same layers, as [clockwise, 180 degrees, counter-clockwise]
+const MOVE_FAMILIES: [[CubeMove; 3]; 18] = [
+    [CubeMove::U, CubeMove::U2, CubeMove::UPrime],
+    [CubeMove::D, CubeMove::D2, CubeMove::DPrime],
+    [CubeMove::L, CubeMove::L2, CubeMove::LPrime],
+    [CubeMove::R, CubeMove::R2, CubeMove::RPrime],
+    [CubeMove::F, CubeMove::F2, CubeMove::FPrime],
+    [CubeMove::B, CubeMove::B2, CubeMove::BPrime],
+    [CubeMove::M, CubeMove::M2, CubeMove::MPrime],
+    [CubeMove::E, CubeMove::E2, CubeMove::EPrime],
+    [CubeMove::S, CubeMove::S2, CubeMove::SPrime],
+    [CubeMove::X, CubeMove::X2, CubeMove::XPrime],
+    [CubeMove::Y, CubeMove::Y2, CubeMove::YPrime],
+    [CubeMove::Z, CubeMove::Z2, CubeMove::ZPrime],
+    [CubeMove::Uw, CubeMove::Uw2, CubeMove::UwPrime],
+    [CubeMove::Dw, CubeMove::Dw2, CubeMove::DwPrime],
+    [CubeMove::Lw, CubeMove::Lw2, CubeMove::LwPrime],
+    [CubeMove::Rw, CubeMove::Rw2, CubeMove::RwPrime],
+    [CubeMove::Fw, CubeMove::Fw2, CubeMove::FwPrime],
+    [CubeMove::Bw, CubeMove::Bw2, CubeMove::BwPrime],
+];
+
+// Singmaster name of each family in MOVE_FAMILIES
+const MOVE_FAMILY_NAMES: [&str; 18] = [
+    "U", "D", "L", "R", "F", "B", "M", "E", "S", "x", "y", "z", "Uw", "Dw", "Lw", "Rw", "Fw", "Bw",
+];
+
 impl CubeMove {
     // convert a move Object into a Direction and Rotation Angle
     fn to_direction_and_degree(&self) -> Option<(Direction, f32)> {
@@ -101,7 +240,410 @@ impl CubeMove {
             CubeMove::R2 => Some((Direction::Right, 180.0)),
             CubeMove::F2 => Some((Direction::Front, 180.0)),
             CubeMove::B2 => Some((Direction::Back, 180.0)),
+            CubeMove::M => Some((Direction::Left, -90.0)),
+            CubeMove::E => Some((Direction::Down, -90.0)),
+            CubeMove::S => Some((Direction::Front, -90.0)),
+            CubeMove::MPrime => Some((Direction::Left, 90.0)),
+            CubeMove::EPrime => Some((Direction::Down, 90.0)),
+            CubeMove::SPrime => Some((Direction::Front, 90.0)),
+            CubeMove::M2 => Some((Direction::Left, 180.0)),
+            CubeMove::E2 => Some((Direction::Down, 180.0)),
+            CubeMove::S2 => Some((Direction::Front, 180.0)),
+            CubeMove::X => Some((Direction::Right, -90.0)),
+            CubeMove::Y => Some((Direction::Up, -90.0)),
+            CubeMove::Z => Some((Direction::Front, -90.0)),
+            CubeMove::XPrime => Some((Direction::Right, 90.0)),
+            CubeMove::YPrime => Some((Direction::Up, 90.0)),
+            CubeMove::ZPrime => Some((Direction::Front, 90.0)),
+            CubeMove::X2 => Some((Direction::Right, 180.0)),
+            CubeMove::Y2 => Some((Direction::Up, 180.0)),
+            CubeMove::Z2 => Some((Direction::Front, 180.0)),
+            CubeMove::Uw => Some((Direction::Up, -90.0)),
+            CubeMove::Dw => Some((Direction::Down, -90.0)),
+            CubeMove::Lw => Some((Direction::Left, -90.0)),
+            CubeMove::Rw => Some((Direction::Right, -90.0)),
+            CubeMove::Fw => Some((Direction::Front, -90.0)),
+            CubeMove::Bw => Some((Direction::Back, -90.0)),
+            CubeMove::UwPrime => Some((Direction::Up, 90.0)),
+            CubeMove::DwPrime => Some((Direction::Down, 90.0)),
+            CubeMove::LwPrime => Some((Direction::Left, 90.0)),
+            CubeMove::RwPrime => Some((Direction::Right, 90.0)),
+            CubeMove::FwPrime => Some((Direction::Front, 90.0)),
+            CubeMove::BwPrime => Some((Direction::Back, 90.0)),
+            CubeMove::Uw2 => Some((Direction::Up, 180.0)),
+            CubeMove::Dw2 => Some((Direction::Down, 180.0)),
+            CubeMove::Lw2 => Some((Direction::Left, 180.0)),
+            CubeMove::Rw2 => Some((Direction::Right, 180.0)),
+            CubeMove::Fw2 => Some((Direction::Front, 180.0)),
+            CubeMove::Bw2 => Some((Direction::Back, 180.0)),
             CubeMove::Reset => None,
+            CubeMove::Layered(_, base_move) => base_move.to_direction_and_degree(),
+        }
+    }
+
+    // the layers turned by a move on a cube of the given size, counted from the face returned by to_direction_and_degree
+    fn to_layers(&self, size: usize) -> RangeInclusive<usize> {
+        match self {
+            CubeMove::M
+            | CubeMove::E
+            | CubeMove::S
+            | CubeMove::MPrime
+            | CubeMove::EPrime
+            | CubeMove::SPrime
+            | CubeMove::M2
+            | CubeMove::E2
+            | CubeMove::S2 => 1..=size.saturating_sub(2),
+            CubeMove::X
+            | CubeMove::Y
+            | CubeMove::Z
+            | CubeMove::XPrime
+            | CubeMove::YPrime
+            | CubeMove::ZPrime
+            | CubeMove::X2
+            | CubeMove::Y2
+            | CubeMove::Z2 => 0..=size - 1,
+            CubeMove::Uw
+            | CubeMove::Dw
+            | CubeMove::Lw
+            | CubeMove::Rw
+            | CubeMove::Fw
+            | CubeMove::Bw
+            | CubeMove::UwPrime
+            | CubeMove::DwPrime
+            | CubeMove::LwPrime
+            | CubeMove::RwPrime
+            | CubeMove::FwPrime
+            | CubeMove::BwPrime
+            | CubeMove::Uw2
+            | CubeMove::Dw2
+            | CubeMove::Lw2
+            | CubeMove::Rw2
+            | CubeMove::Fw2
+            | CubeMove::Bw2 => 0..=1,
+            CubeMove::Layered(layer, base_move) => match base_move.to_layers(size) {
+                // a prefixed wide turn takes that many outer layers, a prefixed face turn only that layer
+                wide_layers if *wide_layers.end() > 0 => 0..=layer - 1,
+                _ => layer - 1..=layer - 1,
+            },
+            _ => 0..=0,
+        }
+    }
+
+    // which family in MOVE_FAMILIES a move belongs to and how many clockwise quarter turns it makes
+    fn to_family_and_turns(&self) -> Option<(usize, usize)> {
+        MOVE_FAMILIES
+            .iter()
+            .enumerate()
+            .find_map(|(family, moves)| {
+                moves
+                    .iter()
+                    .position(|m| m == self)
+                    .map(|index| (family, index + 1))
+            })
+    }
+
+    // look up a family by its Singmaster name, like R, Rw, M or x
+    pub(crate) fn family_from_name(name: &str) -> Option<usize> {
+        MOVE_FAMILY_NAMES
+            .iter()
+            .position(|family_name| *family_name == name)
+    }
+
+    // the move of a family that makes the given number of clockwise quarter turns, None for a full turn
+    pub(crate) fn from_family_and_turns(family: usize, turns: usize) -> Option<CubeMove> {
+        match turns % 4 {
+            0 => None,
+            turns => Some(MOVE_FAMILIES[family][turns - 1].clone()),
+        }
+    }
+
+    // the move that undoes this one
+    pub fn inverse(&self) -> CubeMove {
+        match self {
+            CubeMove::Layered(layer, base_move) => {
+                CubeMove::Layered(*layer, Box::new(base_move.inverse()))
+            }
+            _ => match self.to_family_and_turns() {
+                Some((family, turns)) => {
+                    CubeMove::from_family_and_turns(family, 4 - turns).unwrap()
+                }
+                None => self.clone(),
+            },
+        }
+    }
+
+    // the axis a move turns around as 0, 1 or 2 for x, y or z, moves on the same axis commute
+    pub(crate) fn axis(&self) -> Option<usize> {
+        self.to_direction_and_degree()
+            .map(|(direction, _)| direction.grid_vector().iamax())
+    }
+
+    // the layers a move turns, as its layer prefix and family, R and R' turn the same layers but R and L don't
+    pub(crate) fn layer_group(&self) -> Option<(usize, usize)> {
+        let (layer, base_move) = match self {
+            CubeMove::Layered(layer, base_move) => (*layer, base_move.as_ref()),
+            _ => (0, self),
+        };
+        base_move
+            .to_family_and_turns()
+            .map(|(family, _)| (layer, family))
+    }
+
+    // the move turning the same kind of layers around a different direction, by the given clockwise quarter turns
+    fn from_direction_and_layers(
+        direction: &Direction,
+        turns: usize,
+        layers: &RangeInclusive<usize>,
+    ) -> Option<CubeMove> {
+        let opposite = direction.opposite();
+        MOVE_FAMILIES
+            .iter()
+            .enumerate()
+            .find_map(|(family, moves)| {
+                let (family_direction, _) = moves[0].to_direction_and_degree()?;
+                if moves[0].to_layers(MAX_CUBE_SIZE) != *layers {
+                    None
+                } else if family_direction == *direction {
+                    CubeMove::from_family_and_turns(family, turns)
+                } else if family_direction == opposite
+                    && layers.start() + layers.end() == MAX_CUBE_SIZE - 1
+                {
+                    // layers symmetric around the center (slices and rotations) can be turned from the other side instead
+                    CubeMove::from_family_and_turns(family, 4 - turns % 4)
+                } else {
+                    None
+                }
+            })
+    }
+
+    // the same move after transforming the space it happens in, reflecting it when the transform is a mirror
+    pub(crate) fn transformed(&self, transform: &Matrix3<i32>) -> CubeMove {
+        if let CubeMove::Layered(layer, base_move) = self {
+            return CubeMove::Layered(*layer, Box::new(base_move.transformed(transform)));
+        }
+        match self.to_direction_and_degree() {
+            Some((face_direction, rotation_theta)) => {
+                let direction =
+                    Direction::from_grid_vector(transform * face_direction.grid_vector()).unwrap();
+                // a mirror turns clockwise into counter-clockwise
+                let turns = ((-rotation_theta / 90.0).round() as i32
+                    * transform.cast::<f32>().determinant() as i32)
+                    .rem_euclid(4) as usize;
+                CubeMove::from_direction_and_layers(
+                    &direction,
+                    turns,
+                    &self.to_layers(MAX_CUBE_SIZE),
+                )
+                .unwrap()
+            }
+            None => self.clone(),
+        }
+    }
+
+    // whether every layer the move names is on a cube of the given size, 4R is not on a 3x3
+    pub fn fits(&self, size: usize) -> bool {
+        match self {
+            CubeMove::Layered(layer, _) => *layer <= size,
+            _ => true,
+        }
+    }
+
+    // how many moves this counts as in the metric on a cube of the given size
+    // a move that doesn't fit the cube turns nothing and counts as nothing
+    pub fn count(&self, metric: Metric, size: usize) -> usize {
+        let Some((_, rotation_theta)) = self.to_direction_and_degree() else {
+            return 0;
+        };
+        if !self.fits(size) {
+            return 0;
+        }
+        let layers = self.to_layers(size);
+        let turns_outer_layer = *layers.start() == 0 || *layers.end() == size - 1;
+        let turns_whole_cube = *layers.start() == 0 && *layers.end() >= size - 1;
+        // an inner slice is the same as turning the two outer blocks around it, so face turn metrics count it twice
+        let face_turns = if turns_outer_layer { 1 } else { 2 };
+        let quarter_turns = if rotation_theta.abs() == 180.0 { 2 } else { 1 };
+        match metric {
+            Metric::Etm => 1,
+            _ if turns_whole_cube || layers.is_empty() => 0,
+            Metric::Stm => 1,
+            Metric::Htm => face_turns,
+            Metric::Qtm => face_turns * quarter_turns,
+        }
+    }
+
+    // the move seen in a mirror across the M, E or S plane, R becomes L' for the M plane
+    pub fn mirrored(&self, plane: &MirrorPlane) -> CubeMove {
+        let axis = match plane {
+            MirrorPlane::M => 0,
+            MirrorPlane::E => 1,
+            MirrorPlane::S => 2,
+        };
+        let mut reflection = Matrix3::identity();
+        reflection[(axis, axis)] = -1;
+        self.transformed(&reflection)
+    }
+
+    // what this move does when made after a rotation, expressed without the rotation
+    // after a y the Right face holds what used to be the Back face, so R becomes B
+    pub fn rotated(&self, rotation: &CubeMove) -> CubeMove {
+        match rotation.to_direction_and_degree() {
+            Some((rotation_axis, rotation_theta)) => {
+                self.transformed(&quarter_turn_matrix(&rotation_axis, rotation_theta).transpose())
+            }
+            None => self.clone(),
+        }
+    }
+}
+
+// planes a sequence can be mirrored across, named after the slice that sits in them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MirrorPlane {
+    M, // left and right swap
+    E, // up and down swap
+    S, // front and back swap
+}
+
+// Ways of counting the moves of a sequence, every one of them counts an outer face quarter turn as one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Serialize)]
+pub enum Metric {
+    Htm, // half turn metric: any turn of an outer block of layers is one move, slices two, rotations none
+    Qtm, // quarter turn metric: like the half turn metric but half turns count twice
+    Stm, // slice turn metric: any turn of a block of layers is one move, slices included, rotations none
+    Etm, // execution turn metric: every move made is one, rotations included
+}
+
+impl Metric {
+    // look up a metric by its usual abbreviation, like htm or QTM
+    pub fn from_name(name: &str) -> Option<Metric> {
+        Metric::iter().find(|metric| format!("{:?}", metric).eq_ignore_ascii_case(name))
+    }
+}
+
+// How far a turn has got at each point of its animation, named in query strings as linear, ease-in-out or cubic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromFormField)]
+pub enum Easing {
+    Linear, // the same speed all the way
+    #[field(value = "ease-in-out")]
+    EaseInOut, // speeds up and slows down along half a sine wave
+    Cubic,  // speeds up and slows down more sharply, along two cubic curves
+}
+
+impl Easing {
+    // the part of the turn done after the given part of its time, both from 0 to 1
+    pub fn progress(&self, time: f32) -> f32 {
+        match self {
+            Easing::Linear => time,
+            Easing::EaseInOut => (1.0 - (time * std::f32::consts::PI).cos()) / 2.0,
+            Easing::Cubic if time < 0.5 => 4.0 * time.powi(3),
+            Easing::Cubic => 1.0 - (2.0 - 2.0 * time).powi(3) / 2.0,
+        }
+    }
+}
+
+// The length of a sequence in every metric
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MoveCounts {
+    pub htm: usize,
+    pub qtm: usize,
+    pub stm: usize,
+    pub etm: usize,
+}
+
+impl MoveCounts {
+    pub fn of(moves: &[CubeMove], size: usize) -> Self {
+        MoveCounts {
+            htm: length(moves, Metric::Htm, size),
+            qtm: length(moves, Metric::Qtm, size),
+            stm: length(moves, Metric::Stm, size),
+            etm: length(moves, Metric::Etm, size),
+        }
+    }
+}
+
+// how many moves a sequence counts as in the metric on a cube of the given size
+pub fn length(moves: &[CubeMove], metric: Metric, size: usize) -> usize {
+    moves.iter().map(|m| m.count(metric, size)).sum()
+}
+
+// the moves that undo a sequence
+pub fn invert(moves: &[CubeMove]) -> Vec<CubeMove> {
+    moves.iter().rev().map(|m| m.inverse()).collect()
+}
+
+// cancel and merge moves, R R becomes R2, R R' disappears and U D U becomes U2 D
+// moves around the same axis commute, so a move merges with the last matching move as long as only
+// moves around the same axis sit in between
+pub fn simplify(moves: &[CubeMove]) -> Vec<CubeMove> {
+    let mut simplified: Vec<CubeMove> = Vec::new();
+    for cube_move in moves {
+        let (layer, base_move) = match cube_move {
+            CubeMove::Layered(layer, base_move) => (*layer, base_move.as_ref()),
+            _ => (0, cube_move),
+        };
+        let (family, turns) = match (base_move.to_family_and_turns(), cube_move.axis()) {
+            (Some(family_and_turns), Some(_)) => family_and_turns,
+            _ => {
+                simplified.push(cube_move.clone());
+                continue;
+            }
+        };
+
+        let same_axis_start = simplified
+            .iter()
+            .rposition(|previous| previous.axis() != cube_move.axis())
+            .map_or(0, |index| index + 1);
+        let matching = (same_axis_start..simplified.len())
+            .rev()
+            .find(|&index| simplified[index].layer_group() == Some((layer, family)));
+
+        let (index, total_turns) = match matching {
+            Some(index) => {
+                let previous = simplified.remove(index);
+                let previous_base = match &previous {
+                    CubeMove::Layered(_, base_move) => base_move.as_ref(),
+                    previous => previous,
+                };
+                (
+                    index,
+                    previous_base.to_family_and_turns().unwrap().1 + turns,
+                )
+            }
+            None => (simplified.len(), turns),
+        };
+        if let Some(merged) = CubeMove::from_family_and_turns(family, total_turns) {
+            let merged = match layer {
+                0 => merged,
+                layer => CubeMove::Layered(layer, Box::new(merged)),
+            };
+            simplified.insert(index, merged);
+        }
+    }
+    simplified
+}
+
+// mirror a sequence across the M, E or S plane, useful for turning a right hand alg into a left hand one
+pub fn mirror(moves: &[CubeMove], plane: &MirrorPlane) -> Vec<CubeMove> {
+    moves.iter().map(|m| m.mirrored(plane)).collect()
+}
+
+// re-express a sequence made after a rotation without the rotation, y followed by the sequence does the same as the result followed by y
+// so y R U R' becomes B U B' (plus the y itself, which only changes how the cube is held)
+pub fn rotate(moves: &[CubeMove], rotation: &CubeMove) -> Vec<CubeMove> {
+    moves.iter().map(|m| m.rotated(rotation)).collect()
+}
+
+// write moves in standard notation, R, R2, R', Rw, 2R, 3Rw' and so on
+impl fmt::Display for CubeMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CubeMove::Layered(layer, base_move) => write!(f, "{}{}", layer, base_move),
+            CubeMove::Reset => write!(f, "Reset"),
+            _ => {
+                let (family, turns) = self.to_family_and_turns().unwrap();
+                let suffix = ["", "2", "'"][turns - 1];
+                write!(f, "{}{}", MOVE_FAMILY_NAMES[family], suffix)
+            }
         }
     }
 }
@@ -111,6 +653,24 @@ impl<'r> FromParam<'r> for CubeMove {
     type Error = &'r str;
 
     fn from_param(param: &'r str) -> Result<Self, Self::Error> {
+        // layer prefixed moves like 2R or 3RwPrime
+        let base_param = param.trim_start_matches(|c: char| c.is_ascii_digit());
+        if base_param.len() < param.len() {
+            let layer: usize = param[..param.len() - base_param.len()]
+                .parse()
+                .map_err(|_| param)?;
+            let base_move = CubeMove::from_param(base_param).map_err(|_| param)?;
+            // only outer face turns and Rw style wide turns take a prefix, r already means two layers
+            let is_face_turn = base_move.to_layers(MAX_CUBE_SIZE) == (0..=0);
+            let is_wide_turn =
+                base_param.contains('w') && base_move.to_layers(MAX_CUBE_SIZE) == (0..=1);
+            if layer == 0 || !(is_face_turn || is_wide_turn) || matches!(base_move, CubeMove::Reset)
+            {
+                return Err(param);
+            }
+            return Ok(CubeMove::Layered(layer, Box::new(base_move)));
+        }
+
         match param {
             "U" => Ok(CubeMove::U),
             "D" => Ok(CubeMove::D),
@@ -130,13 +690,49 @@ impl<'r> FromParam<'r> for CubeMove {
             "R2" => Ok(CubeMove::R2),
             "F2" => Ok(CubeMove::F2),
             "B2" => Ok(CubeMove::B2),
+            "M" => Ok(CubeMove::M),
+            "E" => Ok(CubeMove::E),
+            "S" => Ok(CubeMove::S),
+            "MPrime" => Ok(CubeMove::MPrime),
+            "EPrime" => Ok(CubeMove::EPrime),
+            "SPrime" => Ok(CubeMove::SPrime),
+            "M2" => Ok(CubeMove::M2),
+            "E2" => Ok(CubeMove::E2),
+            "S2" => Ok(CubeMove::S2),
+            "x" => Ok(CubeMove::X),
+            "y" => Ok(CubeMove::Y),
+            "z" => Ok(CubeMove::Z),
+            "xPrime" => Ok(CubeMove::XPrime),
+            "yPrime" => Ok(CubeMove::YPrime),
+            "zPrime" => Ok(CubeMove::ZPrime),
+            "x2" => Ok(CubeMove::X2),
+            "y2" => Ok(CubeMove::Y2),
+            "z2" => Ok(CubeMove::Z2),
+            "Uw" | "u" => Ok(CubeMove::Uw),
+            "UwPrime" | "uPrime" => Ok(CubeMove::UwPrime),
+            "Uw2" | "u2" => Ok(CubeMove::Uw2),
+            "Dw" | "d" => Ok(CubeMove::Dw),
+            "DwPrime" | "dPrime" => Ok(CubeMove::DwPrime),
+            "Dw2" | "d2" => Ok(CubeMove::Dw2),
+            "Lw" | "l" => Ok(CubeMove::Lw),
+            "LwPrime" | "lPrime" => Ok(CubeMove::LwPrime),
+            "Lw2" | "l2" => Ok(CubeMove::Lw2),
+            "Rw" | "r" => Ok(CubeMove::Rw),
+            "RwPrime" | "rPrime" => Ok(CubeMove::RwPrime),
+            "Rw2" | "r2" => Ok(CubeMove::Rw2),
+            "Fw" | "f" => Ok(CubeMove::Fw),
+            "FwPrime" | "fPrime" => Ok(CubeMove::FwPrime),
+            "Fw2" | "f2" => Ok(CubeMove::Fw2),
+            "Bw" | "b" => Ok(CubeMove::Bw),
+            "BwPrime" | "bPrime" => Ok(CubeMove::BwPrime),
+            "Bw2" | "b2" => Ok(CubeMove::Bw2),
             "Reset" => Ok(CubeMove::Reset),
             _ => Err(param),
         }
     }
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
 pub enum StickerColor {
     White,
     Yellow,
@@ -144,39 +740,230 @@ pub enum StickerColor {
     Red,
     Green,
     Blue,
+    Rgb(u8, u8, u8), // any other sticker color, red, green and blue from 0 to 255
+}
+
+impl StickerColor {
+    // a color by its name in any case, or any other color as #rrggbb with or without the #
+    pub fn parse(text: &str) -> Option<StickerColor> {
+        let text = text.trim();
+        let named = [
+            StickerColor::White,
+            StickerColor::Yellow,
+            StickerColor::Orange,
+            StickerColor::Red,
+            StickerColor::Green,
+            StickerColor::Blue,
+        ]
+        .into_iter()
+        .find(|color| format!("{:?}", color).eq_ignore_ascii_case(text));
+        if named.is_some() {
+            return named;
+        }
+        let hex = text.strip_prefix('#').unwrap_or(text);
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        Some(StickerColor::Rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+// The color of the stickers that start on each face, chosen when a cube is made
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct ColorScheme {
+    pub up: StickerColor,
+    pub down: StickerColor,
+    pub left: StickerColor,
+    pub right: StickerColor,
+    pub front: StickerColor,
+    pub back: StickerColor,
+}
+
+impl ColorScheme {
+    // white opposite yellow, green opposite blue and red opposite orange, the scheme of most cubes sold today
+    pub fn western() -> Self {
+        ColorScheme {
+            up: StickerColor::White,
+            down: StickerColor::Yellow,
+            left: StickerColor::Orange,
+            right: StickerColor::Red,
+            front: StickerColor::Green,
+            back: StickerColor::Blue,
+        }
+    }
+
+    // white opposite blue and green opposite yellow
+    pub fn japanese() -> Self {
+        ColorScheme {
+            down: StickerColor::Blue,
+            back: StickerColor::Yellow,
+            ..ColorScheme::western()
+        }
+    }
+
+    // a preset by name (western or japanese), or six colors for the U, R, F, D, L and B faces separated by commas
+    pub fn parse(text: &str) -> Option<ColorScheme> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "western" => return Some(ColorScheme::western()),
+            "japanese" => return Some(ColorScheme::japanese()),
+            _ => {}
+        }
+        let colors = text
+            .split(',')
+            .map(StickerColor::parse)
+            .collect::<Option<Vec<_>>>()?;
+        let [up, right, front, down, left, back] = colors.try_into().ok()?;
+        Some(ColorScheme {
+            up,
+            down,
+            left,
+            right,
+            front,
+            back,
+        })
+    }
+
+    // the color of the stickers that start on the side
+    pub(crate) fn color_of(&self, side: &Direction) -> StickerColor {
+        match side {
+            Direction::Up => self.up.clone(),
+            Direction::Down => self.down.clone(),
+            Direction::Left => self.left.clone(),
+            Direction::Right => self.right.clone(),
+            Direction::Front => self.front.clone(),
+            Direction::Back => self.back.clone(),
+        }
+    }
+}
+
+impl Default for ColorScheme {
+    fn default() -> Self {
+        ColorScheme::western()
+    }
+}
+
+pub const MIN_CUBE_SIZE: usize = 2;
+pub const MAX_CUBE_SIZE: usize = 7;
+
+// order of the faces returned by Cube::unwrap, laid out as a net: Up on top, Left Front Right Back in the middle row, Down on the bottom
+const UNWRAP_ORDER: [Direction; 6] = [
+    Direction::Up,
+    Direction::Left,
+    Direction::Front,
+    Direction::Right,
+    Direction::Back,
+    Direction::Down,
+];
+
+// order of the faces in a facelet string, see Cube::to_facelets
+const FACELET_ORDER: [Direction; 6] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Front,
+    Direction::Down,
+    Direction::Left,
+    Direction::Back,
+];
+
+// The side each center color started on, paired with the side its center is on now
+pub(crate) struct CenterSides {
+    sides: Vec<(Direction, Direction)>,
+    scheme: ColorScheme,
 }
 
-// map to get initial color based on face direction
-fn direction_to_color(direction: &Direction) -> StickerColor {
-    match direction {
-        Direction::Up => StickerColor::White,
-        Direction::Down => StickerColor::Yellow,
-        Direction::Left => StickerColor::Orange,
-        Direction::Right => StickerColor::Red,
-        Direction::Front => StickerColor::Green,
-        Direction::Back => StickerColor::Blue,
+impl CenterSides {
+    // the face a sticker that started on initial_side belongs to
+    pub(crate) fn face_of(&self, initial_side: &Direction) -> Direction {
+        self.sides
+            .iter()
+            .find(|(center_initial_side, _)| center_initial_side == initial_side)
+            .map_or(initial_side.clone(), |(_, side)| side.clone())
+    }
+
+    // the color of the center on a face, or of the face on a solved cube without fixed centers
+    pub(crate) fn color_on(&self, face: &Direction) -> StickerColor {
+        self.sides
+            .iter()
+            .find(|(_, side)| side == face)
+            .map_or(self.scheme.color_of(face), |(initial_side, _)| {
+                self.scheme.color_of(initial_side)
+            })
+    }
+
+    // whether turning a solved cube as a whole puts the centers where they are, always true without fixed centers
+    pub(crate) fn is_rotation(&self) -> bool {
+        proper_rotations().iter().any(|rotation| {
+            self.sides.iter().all(|(initial_side, side)| {
+                rotation * initial_side.grid_vector() == side.grid_vector()
+            })
+        })
+    }
+}
+
+// Reasons a facelet string can't be read
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum FaceletError {
+    InvalidLength(usize), // must be 6 faces of NxN letters, 54 for a 3x3
+    InvalidCharacter { index: usize, character: char }, // only U, R, F, D, L and B are allowed, and X in patterns
+}
+
+impl fmt::Display for FaceletError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FaceletError::InvalidLength(length) => {
+                write!(f, "{} letters is not 6 faces of a cube", length)
+            }
+            FaceletError::InvalidCharacter { index, character } => {
+                write!(f, "'{}' at {} is not a face letter", character, index)
+            }
+        }
     }
 }
 
+// the letters of a facelet string as the side each sticker belongs to, None for the X of a sticker left out of a pattern
+pub(crate) fn parse_facelets(
+    facelets: &str,
+    masked: bool,
+) -> Result<(usize, Vec<Option<Direction>>), FaceletError> {
+    let letters: Vec<char> = facelets.trim().chars().collect();
+    let size = (MIN_CUBE_SIZE..=MAX_CUBE_SIZE)
+        .find(|size| 6 * size * size == letters.len())
+        .ok_or(FaceletError::InvalidLength(letters.len()))?;
+    let sides = letters
+        .iter()
+        .enumerate()
+        .map(|(index, letter)| match Direction::from_letter(*letter) {
+            Some(direction) => Ok(Some(direction)),
+            None if masked && *letter == 'X' => Ok(None),
+            None => Err(FaceletError::InvalidCharacter {
+                index,
+                character: *letter,
+            }),
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((size, sides))
+}
+
 // Rough implementation of UVMapping
 // Use this struct so that we can "unwrap" our 3D cube
 // This struct contains helper data and methods to convert our 3D representation into a 3D representation
 #[derive(Debug, Serialize, Clone)]
 struct UvMap {
-    xy_start: (Vector2<f32>, Vector3<f32>),
-    x_end: (Vector2<f32>, Vector3<f32>),
-    y_end: (Vector2<f32>, Vector3<f32>),
-    xy_end: (Vector2<f32>, Vector3<f32>),
-    x_slope: Vector3<f32>,
-    y_slope: Vector3<f32>,
+    xy_start: (Vector2<i32>, Vector3<i32>),
+    x_end: (Vector2<i32>, Vector3<i32>),
+    y_end: (Vector2<i32>, Vector3<i32>),
+    xy_end: (Vector2<i32>, Vector3<i32>),
+    x_slope: Vector3<i32>,
+    y_slope: Vector3<i32>,
 }
 
 impl UvMap {
     fn new(
-        xy_start: (Vector2<f32>, Vector3<f32>),
-        x_end: (Vector2<f32>, Vector3<f32>),
-        y_end: (Vector2<f32>, Vector3<f32>),
-        xy_end: (Vector2<f32>, Vector3<f32>),
+        xy_start: (Vector2<i32>, Vector3<i32>),
+        x_end: (Vector2<i32>, Vector3<i32>),
+        y_end: (Vector2<i32>, Vector3<i32>),
+        xy_end: (Vector2<i32>, Vector3<i32>),
     ) -> Self {
         let x_slope = (x_end.1 - xy_start.1) / (x_end.0[0] - xy_start.0[0]);
         let y_slope = (y_end.1 - xy_start.1) / (y_end.0[1] - xy_start.0[1]);
@@ -194,11 +981,11 @@ impl UvMap {
     // get the xyz position from the xy position. This will be used to fill out each face
     // First we will get the face we want to fill
     // then for each cell in the face, we will use this function to get the peice that contains that face
-    fn get_xyz_vector_from_xy_vector(&self, xy_coordinate: Vector2<f32>) -> Vector3<f32> {
+    fn get_xyz_vector_from_xy_vector(&self, xy_coordinate: Vector2<i32>) -> Vector3<i32> {
         // Calculate the change in the 3D vector for the x direction
-        let x_change: Vector3<f32> = (xy_coordinate[0] - self.xy_start.0[0]) * self.x_slope;
+        let x_change: Vector3<i32> = (xy_coordinate[0] - self.xy_start.0[0]) * self.x_slope;
         // Calculate the change in the 3D vector for the y direction
-        let y_change: Vector3<f32> = (xy_coordinate[1] - self.xy_start.0[1]) * self.y_slope;
+        let y_change: Vector3<i32> = (xy_coordinate[1] - self.xy_start.0[1]) * self.y_slope;
 
         // Return the resulting 3D vector by adding the changes to the starting vector
         self.xy_start.1 + x_change + y_change
@@ -217,67 +1004,100 @@ pub struct Cube {
     right_map: UvMap,
     front_map: UvMap,
     back_map: UvMap,
+    size: usize,
+    scheme: ColorScheme,
+    supercube: bool, // the center stickers can be told apart, so the way the centers are turned matters
     pub pieces: Vec<CubePiece>,
 }
 
 impl Cube {
+    // a standard 3x3x3 cube
     pub fn new() -> Self {
+        Cube::with_size(3)
+    }
+
+    // build an NxNxN cube in the western color scheme
+    // panics if the size is outside of MIN_CUBE_SIZE..=MAX_CUBE_SIZE
+    pub fn with_size(size: usize) -> Self {
+        Cube::with_scheme(size, ColorScheme::default())
+    }
+
+    // build an NxNxN cube with stickers in the colors of the scheme, pieces are spaced one unit apart and centered on the origin
+    // panics if the size is outside of MIN_CUBE_SIZE..=MAX_CUBE_SIZE
+    pub fn with_scheme(size: usize, scheme: ColorScheme) -> Self {
+        assert!(
+            (MIN_CUBE_SIZE..=MAX_CUBE_SIZE).contains(&size),
+            "unsupported cube size {}",
+            size
+        );
         let mut pieces: Vec<CubePiece> = Vec::new();
+        // distance from the center to the outer layer in half units, 2 for a 3x3 and 3 for a 4x4
+        let extent = size as i32 - 1;
 
-        for x in -1..=1 {
-            for y in -1..=1 {
-                for z in -1..=1 {
-                    let piece = CubePiece::new(Vector3::new(x as f32, y as f32, z as f32));
-                    pieces.push(piece);
+        for x in 0..size as i32 {
+            for y in 0..size as i32 {
+                for z in 0..size as i32 {
+                    let position = Vector3::new(2 * x, 2 * y, 2 * z).add_scalar(-extent);
+                    let piece = CubePiece::new(position, extent, &scheme);
+                    // hidden pieces inside of the cube have no faces and never show up, so skip them
+                    if !piece.faces.is_empty() {
+                        pieces.push(piece);
+                    }
                 }
             }
         }
 
         // Build a uv_map for each face
+        // o is the outer coordinate and n the last row/column index of the face
+        let o = extent;
+        let n = size as i32 - 1;
         let up_map = UvMap::new(
-            (Vector2::new(0.0, 0.0), Vector3::new(-1.0, 1.0, -1.0)),
-            (Vector2::new(2.0, 0.0), Vector3::new(1.0, 1.0, -1.0)),
-            (Vector2::new(0.0, 2.0), Vector3::new(-1.0, 1.0, 1.0)),
-            (Vector2::new(2.0, 2.0), Vector3::new(1.0, 1.0, 1.0)),
+            (Vector2::new(0, 0), Vector3::new(-o, o, -o)),
+            (Vector2::new(n, 0), Vector3::new(o, o, -o)),
+            (Vector2::new(0, n), Vector3::new(-o, o, o)),
+            (Vector2::new(n, n), Vector3::new(o, o, o)),
         );
 
         let front_map = UvMap::new(
-            (Vector2::new(0.0, 0.0), Vector3::new(-1.0, 1.0, 1.0)),
-            (Vector2::new(2.0, 0.0), Vector3::new(1.0, 1.0, 1.0)),
-            (Vector2::new(0.0, 2.0), Vector3::new(-1.0, -1.0, 1.0)),
-            (Vector2::new(2.0, 2.0), Vector3::new(1.0, -1.0, 1.0)),
+            (Vector2::new(0, 0), Vector3::new(-o, o, o)),
+            (Vector2::new(n, 0), Vector3::new(o, o, o)),
+            (Vector2::new(0, n), Vector3::new(-o, -o, o)),
+            (Vector2::new(n, n), Vector3::new(o, -o, o)),
         );
 
         let down_map = UvMap::new(
-            (Vector2::new(0.0, 0.0), Vector3::new(-1.0, -1.0, 1.0)),
-            (Vector2::new(2.0, 0.0), Vector3::new(1.0, -1.0, 1.0)),
-            (Vector2::new(0.0, 2.0), Vector3::new(-1.0, -1.0, -1.0)),
-            (Vector2::new(2.0, 2.0), Vector3::new(1.0, -1.0, -1.0)),
+            (Vector2::new(0, 0), Vector3::new(-o, -o, o)),
+            (Vector2::new(n, 0), Vector3::new(o, -o, o)),
+            (Vector2::new(0, n), Vector3::new(-o, -o, -o)),
+            (Vector2::new(n, n), Vector3::new(o, -o, -o)),
         );
 
         let left_map = UvMap::new(
-            (Vector2::new(0.0, 0.0), Vector3::new(-1.0, 1.0, -1.0)),
-            (Vector2::new(2.0, 0.0), Vector3::new(-1.0, 1.0, 1.0)),
-            (Vector2::new(0.0, 2.0), Vector3::new(-1.0, -1.0, -1.0)),
-            (Vector2::new(2.0, 2.0), Vector3::new(-1.0, -1.0, 1.0)),
+            (Vector2::new(0, 0), Vector3::new(-o, o, -o)),
+            (Vector2::new(n, 0), Vector3::new(-o, o, o)),
+            (Vector2::new(0, n), Vector3::new(-o, -o, -o)),
+            (Vector2::new(n, n), Vector3::new(-o, -o, o)),
         );
 
         let right_map = UvMap::new(
-            (Vector2::new(0.0, 0.0), Vector3::new(1.0, 1.0, 1.0)),
-            (Vector2::new(2.0, 0.0), Vector3::new(1.0, 1.0, -1.0)),
-            (Vector2::new(0.0, 2.0), Vector3::new(1.0, -1.0, 1.0)),
-            (Vector2::new(2.0, 2.0), Vector3::new(1.0, -1.0, -1.0)),
+            (Vector2::new(0, 0), Vector3::new(o, o, o)),
+            (Vector2::new(n, 0), Vector3::new(o, o, -o)),
+            (Vector2::new(0, n), Vector3::new(o, -o, o)),
+            (Vector2::new(n, n), Vector3::new(o, -o, -o)),
         );
 
         let back_map = UvMap::new(
-            (Vector2::new(0.0, 0.0), Vector3::new(1.0, 1.0, -1.0)),
-            (Vector2::new(2.0, 0.0), Vector3::new(-1.0, 1.0, -1.0)),
-            (Vector2::new(0.0, 2.0), Vector3::new(1.0, -1.0, -1.0)),
-            (Vector2::new(2.0, 2.0), Vector3::new(-1.0, -1.0, -1.0)),
+            (Vector2::new(0, 0), Vector3::new(o, o, -o)),
+            (Vector2::new(n, 0), Vector3::new(-o, o, -o)),
+            (Vector2::new(0, n), Vector3::new(o, -o, -o)),
+            (Vector2::new(n, n), Vector3::new(-o, -o, -o)),
         );
 
         Cube {
             pieces,
+            size,
+            scheme,
+            supercube: false,
             up_map,
             down_map,
             left_map,
@@ -287,74 +1107,299 @@ impl Cube {
         }
     }
 
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn color_scheme(&self) -> &ColorScheme {
+        &self.scheme
+    }
+
+    // the same cube as a supercube, like a picture cube where a center turned in place no longer looks solved
+    pub fn into_supercube(mut self) -> Self {
+        self.supercube = true;
+        self
+    }
+
+    pub fn is_supercube(&self) -> bool {
+        self.supercube
+    }
+
+    // distance from the center of the cube to the center of its outer layer, in half units
+    fn extent(&self) -> i32 {
+        self.size as i32 - 1
+    }
+
     // apply a CubeMove
     pub fn apply_move(&mut self, cube_move: &CubeMove) {
         if let Some((face_direction, rotation_theta)) = cube_move.to_direction_and_degree() {
+            let layers = cube_move.to_layers(self.size);
+            let extent = self.extent();
+            let rotation = quarter_turn_matrix(&face_direction, rotation_theta);
             for piece in self.pieces.iter_mut() {
-                if vector_is_composed_with_direction(piece.get_position(), &face_direction) {
-                    piece.rotate(&face_direction, rotation_theta);
+                if vector_is_in_layers(piece.get_grid_position(), &face_direction, &layers, extent)
+                {
+                    piece.rotate(&rotation);
                 }
             }
         }
     }
 
     // apply a CubeMove and get incremental animation positions
+    // the frames are float approximations of the turn in between, the cube itself is updated exactly by apply_move
+    // frame k of segments is shown after k / segments of the time of the turn, turned as far as the easing says
     pub fn apply_move_with_animation(
         &mut self,
         cube_move: &CubeMove,
         segments: usize,
+        easing: Easing,
     ) -> Vec<Vec<CubePiece>> {
         let mut cube_pieces_animation = vec![];
 
         if let Some((face_direction, rotation_theta)) = cube_move.to_direction_and_degree() {
+            let layers = cube_move.to_layers(self.size);
+            let extent = self.extent();
             // Collect pieces to rotate
-            let pieces_to_rotate_indices: Vec<usize> = self
+            let pieces_to_rotate: Vec<bool> = self
                 .pieces
                 .iter()
-                .enumerate()
-                .filter(|(_, piece)| {
-                    vector_is_composed_with_direction(piece.get_position(), &face_direction)
+                .map(|piece| {
+                    vector_is_in_layers(piece.get_grid_position(), &face_direction, &layers, extent)
                 })
-                .map(|(i, _)| i)
                 .collect();
 
-            for index in 1..segments {
-                let partial_theta = (rotation_theta) / segments as f32;
-                // Apply rotation to the pieces
-                for &piece_index in &pieces_to_rotate_indices {
-                    if let Some(piece) = self.pieces.get_mut(piece_index) {
-                        piece.rotate(&face_direction, partial_theta);
-                    }
-                }
+            for segment in 1..segments {
+                let partial_theta =
+                    rotation_theta * easing.progress(segment as f32 / segments as f32);
+                let partial_rotation = Rotation3::from_axis_angle(
+                    &face_direction.vector(),
+                    partial_theta.to_radians(),
+                );
 
-                // Clone the state of the cube after each partial rotation
-                let cube_pieces = self.pieces.clone();
+                // Rotate a copy of the pieces for this frame
+                let cube_pieces = self
+                    .pieces
+                    .iter()
+                    .zip(pieces_to_rotate.iter())
+                    .map(|(piece, &rotate)| match rotate {
+                        true => piece.partially_rotated(&partial_rotation),
+                        false => piece.clone(),
+                    })
+                    .collect();
                 cube_pieces_animation.push(cube_pieces);
             }
         }
 
+        self.apply_move(cube_move);
         cube_pieces_animation
     }
 
-    fn get_face_piece_by_position(&self, position: Vector3<f32>) -> Option<&CubePiece> {
-        for piece in self.pieces.iter() {
-            if piece.position == position {
-                return Some(piece);
-            }
+    // solved in the standard orientation, every sticker is back on the side it started on
+    // a supercube also needs every piece turned the way it started, which only shows on the centers
+    pub fn is_solved(&self) -> bool {
+        if self.supercube {
+            return self
+                .pieces
+                .iter()
+                .all(|piece| piece.orientation == Matrix3::identity());
         }
-        None
+        self.pieces.iter().all(|piece| {
+            piece
+                .faces
+                .iter()
+                .all(|face| face.side == face.initial_side_direction)
+        })
     }
 
-    // unwrap the 3d cube to get a list of 3x3 dfaces
+    // solved while possibly held in a different orientation, every side of the cube shows a single color
+    // this is what a solved cube looks like after whole cube rotations or slice moves that moved the centers
+    pub fn is_solved_up_to_rotation(&self) -> bool {
+        if self.supercube {
+            return self
+                .pieces
+                .iter()
+                .all(|piece| piece.orientation == self.pieces[0].orientation);
+        }
+        Direction::iter().all(|side| {
+            let mut faces = self
+                .pieces
+                .iter()
+                .flat_map(|piece| piece.faces.iter())
+                .filter(|face| face.side == side);
+            match faces.next() {
+                Some(first) => {
+                    faces.all(|face| face.initial_side_direction == first.initial_side_direction)
+                }
+                None => true,
+            }
+        })
+    }
+
+    fn get_face_piece_by_position(&self, position: Vector3<i32>) -> Option<&CubePiece> {
+        self.pieces
+            .iter()
+            .find(|piece| piece.grid_position == position)
+    }
+
+    // unwrap the 3d cube to get a list of NxN faces
+    // faces are reported by the physical direction they currently point to, not by their color,
+    // so after a whole cube rotation the Up face is whatever face is on top
     pub fn unwrap(&self) -> Vec<Vec<Vec<StickerColor>>> {
-        vec![
-            self.unwrap_face(&Direction::Up),
-            self.unwrap_face(&Direction::Left),
-            self.unwrap_face(&Direction::Front),
-            self.unwrap_face(&Direction::Right),
-            self.unwrap_face(&Direction::Back),
-            self.unwrap_face(&Direction::Down),
-        ]
+        UNWRAP_ORDER
+            .iter()
+            .map(|direction| self.unwrap_face(direction))
+            .collect()
+    }
+
+    // the same view as unwrap, with the slot each sticker started in instead of its color
+    pub fn unwrap_ids(&self) -> Vec<Vec<Vec<StickerId>>> {
+        let homes = self.facelet_positions();
+        UNWRAP_ORDER
+            .iter()
+            .map(|direction| {
+                self.unwrap_face_with(direction, |piece, face| {
+                    self.sticker_id(&homes, piece, face)
+                })
+            })
+            .collect()
+    }
+
+    // the same view as unwrap, with how many quarter turns clockwise each sticker is from upright
+    // a sticker is upright when the edge that was at the top of its face in the net is at the top of the face it is on,
+    // which is how a picture on it has to be drawn, a center on a supercube shows this without moving
+    pub fn unwrap_orientations(&self) -> Vec<Vec<Vec<u8>>> {
+        UNWRAP_ORDER
+            .iter()
+            .map(|direction| {
+                self.unwrap_face_with(direction, |piece, face| {
+                    let turned = piece.orientation * self.net_up(&face.initial_side_direction);
+                    (0..4)
+                        .find(|&turns| {
+                            quarter_turn_matrix(&face.side, -90.0 * turns as f32)
+                                * self.net_up(&face.side)
+                                == turned
+                        })
+                        .unwrap_or(0)
+                })
+            })
+            .collect()
+    }
+
+    // the direction of the top row of a face as laid out in the net
+    fn net_up(&self, side: &Direction) -> Vector3<i32> {
+        -self.get_uv_map(side).y_slope.map(i32::signum)
+    }
+
+    // how many quarter turns clockwise each fixed center is from the way it started, measured against the other
+    // centers so that holding the cube differently doesn't count, empty when the centers aren't where a rotation puts them
+    pub(crate) fn center_twists(&self) -> Vec<(Direction, u8)> {
+        let centers: Vec<&CubePiece> = Direction::iter()
+            .filter_map(|direction| {
+                self.get_face_piece_by_position(direction.grid_vector() * self.extent())
+            })
+            .filter(|piece| piece.faces.len() == 1)
+            .collect();
+        let Some(holding) = proper_rotations().into_iter().find(|rotation| {
+            centers.iter().all(|center| {
+                rotation * center.faces[0].initial_side_direction.grid_vector()
+                    == center.faces[0].side.grid_vector()
+            })
+        }) else {
+            return vec![];
+        };
+        centers
+            .iter()
+            .map(|center| {
+                let side = &center.faces[0].side;
+                let twist = (0..4)
+                    .find(|&turns| {
+                        quarter_turn_matrix(side, -90.0 * turns as f32) * holding
+                            == center.orientation
+                    })
+                    .unwrap_or(0);
+                (side.clone(), twist)
+            })
+            .collect()
+    }
+
+    // turn the center on the face quarter turns clockwise in place, which only shows on a supercube
+    pub(crate) fn turn_center(&mut self, face: &Direction, quarter_turns: u8) {
+        let position = face.grid_vector() * self.extent();
+        if let Some(center) = self
+            .pieces
+            .iter_mut()
+            .find(|piece| piece.grid_position == position && piece.faces.len() == 1)
+        {
+            center.rotate(&quarter_turn_matrix(face, -90.0 * quarter_turns as f32));
+        }
+    }
+
+    // the slot each sticker started in, in the order of the letters of to_facelets
+    pub fn sticker_ids(&self) -> Vec<StickerId> {
+        let homes = self.facelet_positions();
+        FACELET_ORDER
+            .iter()
+            .flat_map(|direction| {
+                self.unwrap_face_with(direction, |piece, face| {
+                    self.sticker_id(&homes, piece, face)
+                })
+            })
+            .flatten()
+            .collect()
+    }
+
+    // a sticker is identified by the home of its piece and the side it started on, homes being the solved facelets
+    fn sticker_id(
+        &self,
+        homes: &[(Vector3<i32>, Direction)],
+        piece: &CubePiece,
+        face: &Face,
+    ) -> StickerId {
+        let home = piece.get_home_grid_position();
+        let extent = self.extent();
+        let faces: Vec<Direction> = Direction::iter()
+            .filter(|direction| vector_is_composed_with_direction(home, direction, extent))
+            .collect();
+        // corners and edges take the names of the Corner and Edge with the same faces, like URF, centers their face
+        let slot: String = Corner::iter()
+            .map(|corner| corner.faces().to_vec())
+            .chain(Edge::iter().map(|edge| edge.faces().to_vec()))
+            .find(|named| {
+                named.len() == faces.len() && faces.iter().all(|face| named.contains(face))
+            })
+            .unwrap_or(faces)
+            .iter()
+            .map(Direction::letter)
+            .collect();
+        let side = &face.initial_side_direction;
+        if self.size <= 3 {
+            return StickerId(format!("{}-{}", slot, side.letter()));
+        }
+        let index = homes
+            .iter()
+            .position(|(position, home_side)| *position == home && home_side == side)
+            .unwrap_or(0)
+            % (self.size * self.size);
+        StickerId(format!(
+            "{}-{}[{},{}]",
+            slot,
+            side.letter(),
+            index / self.size,
+            index % self.size
+        ))
+    }
+
+    // the color of the center currently sitting on each physical face, in unwrap order
+    // cubes with an even size have no fixed centers, so this is empty for them
+    pub fn centers(&self) -> Vec<StickerColor> {
+        UNWRAP_ORDER
+            .iter()
+            .filter_map(|direction| {
+                let center =
+                    self.get_face_piece_by_position(direction.grid_vector() * self.extent())?;
+                center.get_face(direction).map(|face| face.color.clone())
+            })
+            .collect()
     }
 
     fn get_uv_map(&self, face_direction: &Direction) -> &UvMap {
@@ -370,17 +1415,26 @@ impl Cube {
 
     // get the UV map and use it to unwrap the desired face
     fn unwrap_face(&self, face_direction: &Direction) -> Vec<Vec<StickerColor>> {
-        let mut unwraped_face: Vec<Vec<StickerColor>> = Vec::new();
+        self.unwrap_face_with(face_direction, |_, face| face.color.clone())
+    }
+
+    // unwrap a face, reading whatever we need from each sticker
+    fn unwrap_face_with<T>(
+        &self,
+        face_direction: &Direction,
+        read: impl Fn(&CubePiece, &Face) -> T,
+    ) -> Vec<Vec<T>> {
+        let mut unwraped_face: Vec<Vec<T>> = Vec::new();
         let uv_map = self.get_uv_map(face_direction);
 
-        for row in 0..3 {
+        for row in 0..self.size {
             let mut unwrapped_row = Vec::new();
-            for col in 0..3 {
+            for col in 0..self.size {
                 let new_v =
-                    uv_map.get_xyz_vector_from_xy_vector(Vector2::new(col as f32, row as f32));
+                    uv_map.get_xyz_vector_from_xy_vector(Vector2::new(col as i32, row as i32));
                 if let Some(piece) = self.get_face_piece_by_position(new_v) {
                     if let Some(face) = piece.get_face(face_direction) {
-                        unwrapped_row.push(face.color.clone());
+                        unwrapped_row.push(read(piece, face));
                     } else {
                         println!("No sticker found for face_direction {:?}", face_direction);
                     }
@@ -394,52 +1448,152 @@ impl Cube {
         unwraped_face
     }
 
+    // export in the facelet format used by Kociemba and most other solvers and timers
+    // faces in U, R, F, D, L, B order, each read row by row as laid out in the unwrap net,
+    // every sticker written as the letter of the face whose center has its color
+    pub fn to_facelets(&self) -> String {
+        let center_sides = self.center_sides();
+        let letter = |_: &CubePiece, face: &Face| {
+            center_sides.face_of(&face.initial_side_direction).letter()
+        };
+
+        FACELET_ORDER
+            .iter()
+            .flat_map(|direction| self.unwrap_face_with(direction, letter))
+            .flatten()
+            .collect()
+    }
+
+    // the position of the piece and the side of the sticker for each letter of a facelet string
+    pub(crate) fn facelet_positions(&self) -> Vec<(Vector3<i32>, Direction)> {
+        let mut positions = Vec::new();
+        for face_direction in FACELET_ORDER.iter() {
+            let uv_map = self.get_uv_map(face_direction);
+            for row in 0..self.size {
+                for col in 0..self.size {
+                    let position =
+                        uv_map.get_xyz_vector_from_xy_vector(Vector2::new(col as i32, row as i32));
+                    positions.push((position, face_direction.clone()));
+                }
+            }
+        }
+        positions
+    }
+
+    // which face each color belongs to, going by the center with that color
+    // centers may have been moved by slices or rotations, so this follows the centers rather than fixed sides
+    // cubes with an even size have no fixed centers and use the side each sticker started on
+    pub(crate) fn center_sides(&self) -> CenterSides {
+        CenterSides {
+            sides: Direction::iter()
+                .filter_map(|direction| {
+                    let center =
+                        self.get_face_piece_by_position(direction.grid_vector() * self.extent())?;
+                    let face = center.get_face(&direction)?;
+                    Some((face.initial_side_direction.clone(), direction))
+                })
+                .collect(),
+            scheme: self.scheme.clone(),
+        }
+    }
+
+    // import a facelet string, the size of the cube follows from its length (54 letters for a 3x3)
+    // only the format is checked here, the stickers are placed exactly as given even if no real cube could show them
+    pub fn from_facelets(facelets: &str) -> Result<Cube, FaceletError> {
+        Cube::from_facelets_with_scheme(facelets, ColorScheme::default())
+    }
+
+    // import a facelet string onto a cube in the colors of the scheme, the letters name faces so they don't depend on it
+    pub fn from_facelets_with_scheme(
+        facelets: &str,
+        scheme: ColorScheme,
+    ) -> Result<Cube, FaceletError> {
+        let (size, sides) = parse_facelets(facelets, false)?;
+
+        let mut cube = Cube::with_scheme(size, scheme);
+        let extent = cube.extent();
+        // the side each sticker started on, by where it sits now
+        let initial_sides: Vec<(Vector3<i32>, Direction, Direction)> = cube
+            .facelet_positions()
+            .into_iter()
+            .zip(sides)
+            .map(|((position, face_direction), side)| (position, face_direction, side.unwrap()))
+            .collect();
+
+        let scheme = cube.scheme.clone();
+        for piece in cube.pieces.iter_mut() {
+            let position = piece.grid_position;
+            for face in piece.faces.iter_mut() {
+                if let Some((_, _, initial_side)) = initial_sides
+                    .iter()
+                    .find(|(p, side, _)| *p == position && *side == face.side)
+                {
+                    face.initial_side_direction = initial_side.clone();
+                    face.color = scheme.color_of(initial_side);
+                }
+            }
+            piece.reorient_from_faces(extent);
+        }
+
+        Ok(cube)
+    }
+
     pub fn print(&self) {
         let unwraped_faces = self.unwrap();
 
-        for face in 0..6 {
-            for row in 0..3 {
-                for col in 0..3 {
-                    print!("{:?} | ", unwraped_faces[face][row][col]);
+        for face in unwraped_faces.iter() {
+            for row in face.iter() {
+                for color in row.iter() {
+                    print!("{:?} | ", color);
                 }
-                println!("");
+                println!();
                 println!("------- ------- -------");
             }
 
-            println!("");
+            println!();
         }
     }
 }
 
+impl Default for Cube {
+    fn default() -> Self {
+        Cube::new()
+    }
+}
+
 // Cube piece is the individual pieces that make up the rubiks cube
-// 26 in total: 8 corner pieces with 3 faces, 12 edge pieces with 2 faces, and 6 center pieces with 1 face
+// 26 in total on a 3x3: 8 corner pieces with 3 faces, 12 edge pieces with 2 faces, and 6 center pieces with 1 face
+// bigger cubes add more edge and center pieces, a 2x2 only has the 8 corners
 #[derive(Debug, Serialize, Clone)]
 pub struct CubePiece {
-    faces: Vec<Face>, // holds the faces of the CubePiece 
-    position: Vector3<f32>, // holds the position, this will change as the cube is being rotated
-    rotation: Rotation3<f32>, // holds the current rotation of the cube piece this will change as the cube is rotated
+    faces: Vec<Face>,         // holds the faces of the CubePiece
+    position: Vector3<f32>, // holds the position for rendering, derived from grid_position except in animation frames
+    rotation: Rotation3<f32>, // holds the rotation for rendering, derived from orientation except in animation frames
+    #[serde(skip)]
+    grid_position: Vector3<i32>, // exact position in half units, this will change as the cube is being rotated
+    #[serde(skip)]
+    orientation: Matrix3<i32>, // exact rotation as a signed permutation matrix, this will change as the cube is rotated
 }
 
 impl CubePiece {
-    fn new(position: Vector3<f32>) -> Self {
+    fn new(grid_position: Vector3<i32>, extent: i32, scheme: &ColorScheme) -> Self {
         let mut faces: Vec<Face> = Vec::new();
 
         // Based on the initial location of the cube piece we can check what faces this cube should have
         // for example, the Left, Top, Front, piece will have 3 faces. One on the Left, one on the Top, and one on the Front
         for direction in Direction::iter() {
-            match vector_is_composed_with_direction(position, &direction) {
-                true => {
-                    let face = Face::new(direction);
-                    faces.push(face);
-                }
-                false => (),
+            if vector_is_composed_with_direction(grid_position, &direction, extent) {
+                let face = Face::new(direction, scheme);
+                faces.push(face);
             }
         }
 
         CubePiece {
             faces,
-            position: position.into(),
-            rotation: Rotation3::<f32>::identity().into(),
+            position: grid_position.cast::<f32>() / 2.0,
+            rotation: Rotation3::<f32>::identity(),
+            grid_position,
+            orientation: Matrix3::identity(),
         }
     }
 
@@ -447,32 +1601,111 @@ impl CubePiece {
     // this is based on the global cube face direction.
     // For example, if we want to build the left face we will first get all peices on the left, then get the face pointing to the left
     fn get_face(&self, target_cube_face: &Direction) -> Option<&Face> {
-        for (index, face) in self.faces.iter().enumerate() {
-            if face.side == *target_cube_face {
-                return Some(&face);
-            }
-        }
-        None
+        self.faces
+            .iter()
+            .find(|face| face.side == *target_cube_face)
     }
 
-    fn get_position(&self) -> Vector3<f32> {
-        self.position
+    // each sticker of the piece as the side it is on now and the side it started on
+    pub(crate) fn get_stickers(&self) -> Vec<(Direction, Direction)> {
+        self.faces
+            .iter()
+            .map(|face| (face.side.clone(), face.initial_side_direction.clone()))
+            .collect()
     }
 
-    // apply rotation
-    fn rotate(&mut self, rotation_axis: &Direction, rotation_theta: f32) {
-        let rotation_axis_vector = rotation_axis.vector();
-        let rotation =
-            Rotation3::from_axis_angle(&rotation_axis_vector, rotation_theta.to_radians());
+    pub(crate) fn get_grid_position(&self) -> Vector3<i32> {
+        self.grid_position
+    }
+
+    pub(crate) fn get_orientation(&self) -> Matrix3<i32> {
+        self.orientation
+    }
+
+    // where the piece sits on a solved cube, every turn rotates around the center so undoing the orientation gets us back there
+    pub(crate) fn get_home_grid_position(&self) -> Vector3<i32> {
+        self.orientation.transpose() * self.grid_position
+    }
+
+    // move the piece from its home to wherever the given exact rotation takes it
+    pub(crate) fn set_orientation(&mut self, orientation: Matrix3<i32>) {
+        let home_grid_position = self.get_home_grid_position();
+        self.grid_position = orientation * home_grid_position;
+        self.orientation = orientation;
+        self.position = self.grid_position.cast::<f32>() / 2.0;
+        self.rotation = Rotation3::from_matrix_unchecked(self.orientation.cast::<f32>());
+
+        for face in self.faces.iter_mut() {
+            face.set_orientation(&orientation);
+        }
+    }
 
-        let new_position = rotation * self.position;
-        self.position = round_to_nearest_hundredth(&new_position);
-        self.rotation = rotation * self.rotation;
+    // apply an exact quarter turn rotation and refresh the rendering transform from it
+    fn rotate(&mut self, rotation: &Matrix3<i32>) {
+        self.grid_position = rotation * self.grid_position;
+        self.orientation = rotation * self.orientation;
+        self.position = self.grid_position.cast::<f32>() / 2.0;
+        self.rotation = Rotation3::from_matrix_unchecked(self.orientation.cast::<f32>());
 
         for face in self.faces.iter_mut() {
-            face.rotate(rotation_axis, rotation_theta);
+            face.rotate(rotation);
         }
     }
+
+    // work out the orientation from where the stickers started and where they are now
+    // used when stickers are placed directly (for example from a facelet string) instead of by turning
+    // if no rotation fits the stickers the piece can't exist, it keeps the closest match so validation can report it
+    fn reorient_from_faces(&mut self, extent: i32) {
+        let rotations = proper_rotations();
+        let fits_faces = |rotation: &&Matrix3<i32>| {
+            self.faces.iter().all(|face| {
+                **rotation * face.initial_side_direction.grid_vector() == face.side.grid_vector()
+            })
+        };
+        // prefer a rotation that brings the piece from a spot that has the same stickers on a solved cube
+        let starts_on_surface = |rotation: &&Matrix3<i32>| {
+            let home = rotation.transpose() * self.grid_position;
+            Direction::iter()
+                .filter(|direction| vector_is_composed_with_direction(home, direction, extent))
+                .count()
+                == self.faces.len()
+        };
+        let orientation = rotations
+            .iter()
+            .filter(fits_faces)
+            .find(starts_on_surface)
+            .or_else(|| rotations.iter().find(fits_faces))
+            .or_else(|| {
+                rotations.iter().find(|rotation| {
+                    *rotation * self.faces[0].initial_side_direction.grid_vector()
+                        == self.faces[0].side.grid_vector()
+                })
+            })
+            .copied()
+            .unwrap_or_else(Matrix3::identity);
+
+        self.orientation = orientation;
+        self.rotation = Rotation3::from_matrix_unchecked(orientation.cast::<f32>());
+    }
+
+    // copy of the piece part of the way through a turn, only used for animation frames
+    fn partially_rotated(&self, rotation: &Rotation3<f32>) -> CubePiece {
+        let mut piece = self.clone();
+        piece.position = rotation * self.position;
+        piece.rotation = rotation * self.rotation;
+        piece
+    }
+}
+
+// The slot a sticker started in on a solved cube, named by the faces of its piece and then its own face, like URF-U
+// on cubes bigger than 3x3 several pieces share a name, so the row and column the sticker started at is added: UF-U[3,1]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct StickerId(String);
+
+impl fmt::Display for StickerId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
 }
 
 // Face is the entity that will contain the sticker color. THis
@@ -480,37 +1713,200 @@ impl CubePiece {
 struct Face {
     initial_side_direction: Direction, // where was this side initially placed
     side: Direction, // as the cube is being rotated the side of the cube this face is on will change
-    position: Vector3<f32>, // as the cube is being rotated the position of the face will change
-    rotation: Rotation3<f32>, // as the cube is being rotated the rotation of the face will change
     color: StickerColor,
 }
 
 impl Face {
-    fn new(initial_side_direction: Direction) -> Self {
-        let color = direction_to_color(&initial_side_direction);
+    fn new(initial_side_direction: Direction, scheme: &ColorScheme) -> Self {
+        let color = scheme.color_of(&initial_side_direction);
         Face {
             initial_side_direction: initial_side_direction.clone(), // keep track of initial
             side: initial_side_direction.clone(),
             color,
-            position: initial_side_direction.clone().vector().into_inner(),
-            rotation: Rotation3::<f32>::identity().into(),
         }
     }
 
-    fn rotate(&mut self, rotation_axis: &Direction, rotation_theta: f32) {
-        let rotation_axis_vector = rotation_axis.vector();
-        let rotation =
-            Rotation3::from_axis_angle(&rotation_axis_vector, rotation_theta.to_radians());
+    fn rotate(&mut self, rotation: &Matrix3<i32>) {
+        if let Some(rotated_face_direction) =
+            Direction::from_grid_vector(rotation * self.side.grid_vector())
+        {
+            self.side = rotated_face_direction;
+        }
+    }
+
+    fn set_orientation(&mut self, orientation: &Matrix3<i32>) {
+        if let Some(face_direction) =
+            Direction::from_grid_vector(orientation * self.initial_side_direction.grid_vector())
+        {
+            self.side = face_direction;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use std::collections::{HashMap, HashSet};
+
+    // the side each sticker started on, by where the sticker is now and the side it is on, all as letters
+    fn stickers(cube: &Cube) -> HashMap<([i32; 3], char), char> {
+        cube.pieces
+            .iter()
+            .flat_map(|piece| {
+                piece.faces.iter().map(|face| {
+                    (
+                        (piece.get_grid_position().into(), face.side.letter()),
+                        face.initial_side_direction.letter(),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    fn played(size: usize, moves: &[CubeMove]) -> Cube {
+        let mut cube = Cube::with_size(size);
+        for cube_move in moves {
+            cube.apply_move(cube_move);
+        }
+        cube
+    }
+
+    // random moves of every family, layer prefixed ones too on bigger cubes
+    fn random_moves(rng: &mut impl Rng, size: usize, length: usize) -> Vec<CubeMove> {
+        (0..length)
+            .map(|_| {
+                let cube_move = MOVE_FAMILIES[rng.gen_range(0..MOVE_FAMILIES.len())]
+                    [rng.gen_range(0..3)]
+                .clone();
+                let takes_prefix = cube_move.axis().is_some()
+                    && *cube_move.to_layers(size).start() == 0
+                    && cube_move.to_layers(size) != (0..=size - 1);
+                if size > 3 && takes_prefix && rng.gen_bool(0.3) {
+                    CubeMove::Layered(rng.gen_range(1..=size), Box::new(cube_move))
+                } else {
+                    cube_move
+                }
+            })
+            .collect()
+    }
+
+    fn reflected(letter: char, axis: usize) -> char {
+        let direction = Direction::iter().find(|d| d.letter() == letter).unwrap();
+        let mut vector = direction.grid_vector();
+        vector[axis] = -vector[axis];
+        Direction::iter()
+            .find(|direction| direction.grid_vector() == vector)
+            .unwrap()
+            .letter()
+    }
+
+    #[test]
+    fn invert_undoes_a_sequence() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        for size in 2..=5 {
+            for _ in 0..10 {
+                let moves = random_moves(&mut rng, size, 30);
+                let cube = played(size, &[moves.clone(), invert(&moves)].concat());
+                assert!(cube.is_solved(), "{:?}", moves);
+            }
+        }
+    }
+
+    #[test]
+    fn simplify_merges_and_cancels() {
+        assert_eq!(
+            simplify(&[CubeMove::U, CubeMove::D, CubeMove::U]),
+            vec![CubeMove::U2, CubeMove::D]
+        );
+        assert_eq!(simplify(&[CubeMove::R, CubeMove::R]), vec![CubeMove::R2]);
+        assert_eq!(simplify(&[CubeMove::R, CubeMove::RPrime]), vec![]);
+        assert_eq!(
+            simplify(&[CubeMove::R, CubeMove::U, CubeMove::UPrime, CubeMove::R]),
+            vec![CubeMove::R2]
+        );
+
+        let mut rng = ChaCha8Rng::seed_from_u64(2);
+        for size in 2..=5 {
+            for _ in 0..10 {
+                let moves = random_moves(&mut rng, size, 30);
+                assert_eq!(
+                    stickers(&played(size, &simplify(&moves))),
+                    stickers(&played(size, &moves)),
+                    "{:?}",
+                    moves
+                );
+            }
+        }
+    }
 
-        let new_rotated_face_vector = rotation * self.position;
-        let new_rounded_rotated_face_vector = round_to_nearest_hundredth(&new_rotated_face_vector);
+    #[test]
+    fn mirror_plays_the_mirror_image() {
+        assert_eq!(
+            mirror(
+                &[CubeMove::R, CubeMove::U, CubeMove::RPrime],
+                &MirrorPlane::M
+            ),
+            vec![CubeMove::LPrime, CubeMove::UPrime, CubeMove::L]
+        );
 
-        self.position = new_rounded_rotated_face_vector;
-        self.rotation = rotation * self.rotation;
-        if let Some(rotated_face_direcction) =
-            Direction::from_vector(new_rounded_rotated_face_vector)
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        for (axis, plane) in [MirrorPlane::M, MirrorPlane::E, MirrorPlane::S]
+            .iter()
+            .enumerate()
         {
-            self.side = rotated_face_direcction;
+            for size in 2..=4 {
+                let moves = random_moves(&mut rng, size, 30);
+                let original = stickers(&played(size, &moves));
+                let mirrored = stickers(&played(size, &mirror(&moves, plane)));
+                for ((position, side), initial_side) in original {
+                    let mut reflected_position = position;
+                    reflected_position[axis] = -position[axis];
+                    assert_eq!(
+                        mirrored[&(reflected_position, reflected(side, axis))],
+                        reflected(initial_side, axis),
+                        "{:?}",
+                        moves
+                    );
+                }
+            }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn rotate_moves_the_rotation_to_the_end() {
+        assert_eq!(rotate(&[CubeMove::R], &CubeMove::Y), vec![CubeMove::B]);
+
+        let mut rng = ChaCha8Rng::seed_from_u64(4);
+        for rotation in [CubeMove::X, CubeMove::Y, CubeMove::Z, CubeMove::YPrime] {
+            for size in 2..=4 {
+                let moves = random_moves(&mut rng, size, 30);
+                let before = [vec![rotation.clone()], moves.clone()].concat();
+                let after = [rotate(&moves, &rotation), vec![rotation.clone()]].concat();
+                assert_eq!(
+                    stickers(&played(size, &before)),
+                    stickers(&played(size, &after)),
+                    "{:?}",
+                    moves
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn sticker_ids_use_the_piece_names() {
+        let mut cube = Cube::new();
+        let ids: Vec<String> = cube.sticker_ids().iter().map(|id| id.to_string()).collect();
+        assert_eq!(
+            &ids[..9],
+            ["ULB-U", "UB-U", "UBR-U", "UL-U", "U-U", "UR-U", "UFL-U", "UF-U", "URF-U"]
+        );
+        let unique: HashSet<&String> = ids.iter().collect();
+        assert_eq!(unique.len(), 54);
+        cube.apply_move(&CubeMove::R);
+        let ids = cube.sticker_ids();
+        assert_eq!(ids[0].to_string(), "ULB-U");
+        assert_eq!(ids[8].to_string(), "DFR-F");
+    }
+}
@@ -0,0 +1,457 @@
+use crate::cube::{Cube, CubeMove, Direction};
+use nalgebra::{Matrix3, Vector3};
+use serde::Serialize;
+use std::sync::OnceLock;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+// Cubie level representation of a 3x3 cube
+// Instead of tracking pieces in 3D space, this tracks which piece sits in which slot and how it is twisted
+// This is the standard representation used by solvers: corner permutation (cp), corner orientation (co),
+// edge permutation (ep) and edge orientation (eo). Centers are fixed, so they are not part of it.
+
+// Corner slots and pieces, named by the faces they touch going clockwise from the Up or Down face
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Serialize)]
+pub enum Corner {
+    URF,
+    UFL,
+    ULB,
+    UBR,
+    DFR,
+    DLF,
+    DBL,
+    DRB,
+}
+
+// Edge slots and pieces, named by the faces they touch starting from the Up/Down or Front/Back face
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Serialize)]
+pub enum Edge {
+    UR,
+    UF,
+    UL,
+    UB,
+    DR,
+    DF,
+    DL,
+    DB,
+    FR,
+    FL,
+    BL,
+    BR,
+}
+
+impl Corner {
+    // faces of the corner slot in clockwise order, the first one is the face that decides the orientation
+    pub(crate) fn faces(&self) -> [Direction; 3] {
+        match self {
+            Corner::URF => [Direction::Up, Direction::Right, Direction::Front],
+            Corner::UFL => [Direction::Up, Direction::Front, Direction::Left],
+            Corner::ULB => [Direction::Up, Direction::Left, Direction::Back],
+            Corner::UBR => [Direction::Up, Direction::Back, Direction::Right],
+            Corner::DFR => [Direction::Down, Direction::Front, Direction::Right],
+            Corner::DLF => [Direction::Down, Direction::Left, Direction::Front],
+            Corner::DBL => [Direction::Down, Direction::Back, Direction::Left],
+            Corner::DRB => [Direction::Down, Direction::Right, Direction::Back],
+        }
+    }
+
+    // position of the corner slot on a 3x3 in the half units used by the exact piece model
+    fn grid_position(&self) -> Vector3<i32> {
+        self.faces().iter().map(|face| face.grid_vector() * 2).sum()
+    }
+
+    pub(crate) fn from_grid_position(position: Vector3<i32>) -> Option<Corner> {
+        Corner::iter().find(|corner| corner.grid_position() == position)
+    }
+
+    // the twist of this corner piece when it sits in the slot with the given exact rotation
+    // the twist is which of the slot faces the Up/Down sticker of the corner ended up on
+    pub(crate) fn twist_in(&self, slot: Corner, orientation: &Matrix3<i32>) -> u8 {
+        let reference = orientation * self.faces()[0].grid_vector();
+        slot.faces()
+            .iter()
+            .position(|face| face.grid_vector() == reference)
+            .unwrap_or(0) as u8
+    }
+
+    // the exact rotation that takes this corner piece from its home into the slot with the given twist
+    pub(crate) fn orientation_in(&self, slot: Corner, twist: u8) -> Matrix3<i32> {
+        let from = self.faces();
+        let to = slot.faces();
+        // each face of the corner moves to the slot face twist steps further clockwise
+        (0..3)
+            .map(|k| to[(k + twist as usize) % 3].grid_vector() * from[k].grid_vector().transpose())
+            .sum()
+    }
+}
+
+impl Edge {
+    // faces of the edge slot, the first one is the face that decides the orientation
+    pub(crate) fn faces(&self) -> [Direction; 2] {
+        match self {
+            Edge::UR => [Direction::Up, Direction::Right],
+            Edge::UF => [Direction::Up, Direction::Front],
+            Edge::UL => [Direction::Up, Direction::Left],
+            Edge::UB => [Direction::Up, Direction::Back],
+            Edge::DR => [Direction::Down, Direction::Right],
+            Edge::DF => [Direction::Down, Direction::Front],
+            Edge::DL => [Direction::Down, Direction::Left],
+            Edge::DB => [Direction::Down, Direction::Back],
+            Edge::FR => [Direction::Front, Direction::Right],
+            Edge::FL => [Direction::Front, Direction::Left],
+            Edge::BL => [Direction::Back, Direction::Left],
+            Edge::BR => [Direction::Back, Direction::Right],
+        }
+    }
+
+    // position of the edge slot on a 3x3 in the half units used by the exact piece model
+    fn grid_position(&self) -> Vector3<i32> {
+        self.faces().iter().map(|face| face.grid_vector() * 2).sum()
+    }
+
+    pub(crate) fn from_grid_position(position: Vector3<i32>) -> Option<Edge> {
+        Edge::iter().find(|edge| edge.grid_position() == position)
+    }
+}
+
+// the outer face turns, U, U2, U' and so on for each face in the order U R F D L B
+pub const FACE_TURNS: [CubeMove; 18] = [
+    CubeMove::U,
+    CubeMove::U2,
+    CubeMove::UPrime,
+    CubeMove::R,
+    CubeMove::R2,
+    CubeMove::RPrime,
+    CubeMove::F,
+    CubeMove::F2,
+    CubeMove::FPrime,
+    CubeMove::D,
+    CubeMove::D2,
+    CubeMove::DPrime,
+    CubeMove::L,
+    CubeMove::L2,
+    CubeMove::LPrime,
+    CubeMove::B,
+    CubeMove::B2,
+    CubeMove::BPrime,
+];
+
+// Reasons a Cube can't be described as a CubieCube
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum CubieError {
+    UnsupportedSize(usize), // only 3x3 cubes have corners, edges and fixed centers
+    CentersMoved,           // a slice move or whole cube rotation moved the centers away from home
+    InvalidPieces, // a piece is missing or in two slots, or a twist or flip is out of range
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CubieCube {
+    pub cp: [Corner; 8], // corner in each corner slot
+    pub co: [u8; 8],     // clockwise twist of the corner in each slot, 0..3
+    pub ep: [Edge; 12],  // edge in each edge slot
+    pub eo: [u8; 12],    // flip of the edge in each slot, 0..2
+}
+
+impl CubieCube {
+    // the solved cube
+    pub fn identity() -> Self {
+        CubieCube {
+            cp: [
+                Corner::URF,
+                Corner::UFL,
+                Corner::ULB,
+                Corner::UBR,
+                Corner::DFR,
+                Corner::DLF,
+                Corner::DBL,
+                Corner::DRB,
+            ],
+            co: [0; 8],
+            ep: [
+                Edge::UR,
+                Edge::UF,
+                Edge::UL,
+                Edge::UB,
+                Edge::DR,
+                Edge::DF,
+                Edge::DL,
+                Edge::DB,
+                Edge::FR,
+                Edge::FL,
+                Edge::BL,
+                Edge::BR,
+            ],
+            eo: [0; 12],
+        }
+    }
+
+    // the cubie cube of a single move, only outer face turns keep the centers in place
+    pub fn from_move(cube_move: &CubeMove) -> Option<Self> {
+        // turning a whole cube is slow, so the face turns are worked out once and kept
+        static FACE_TURN_CUBIES: OnceLock<Vec<CubieCube>> = OnceLock::new();
+        let face_turn_cubies = FACE_TURN_CUBIES.get_or_init(|| {
+            FACE_TURNS
+                .iter()
+                .map(|face_turn| CubieCube::from_turned_cube(face_turn).unwrap())
+                .collect()
+        });
+        match FACE_TURNS
+            .iter()
+            .position(|face_turn| face_turn == cube_move)
+        {
+            Some(index) => Some(face_turn_cubies[index].clone()),
+            None => CubieCube::from_turned_cube(cube_move),
+        }
+    }
+
+    fn from_turned_cube(cube_move: &CubeMove) -> Option<Self> {
+        let mut cube = Cube::new();
+        cube.apply_move(cube_move);
+        CubieCube::try_from(&cube).ok()
+    }
+
+    // the cubie cube of a sequence of outer face turns, None if any other move is in it
+    pub fn from_moves(moves: &[CubeMove]) -> Option<Self> {
+        moves
+            .iter()
+            .try_fold(CubieCube::identity(), |cubie, cube_move| {
+                Some(cubie.compose(&CubieCube::from_move(cube_move)?))
+            })
+    }
+
+    // the cubie cube of a 3x3 as seen from its centers, which works even after slice moves and rotations
+    // pieces are named after the centers that have their colors, so face turns that solve it solve the cube as it is held
+    pub fn relative_to_centers(cube: &Cube) -> Result<Self, CubieError> {
+        if cube.size() != 3 {
+            return Err(CubieError::UnsupportedSize(cube.size()));
+        }
+        let recolored = Cube::from_facelets(&cube.to_facelets()).unwrap();
+        CubieCube::try_from(&recolored)
+    }
+
+    // apply other after self, so applying a move to a state is state.compose(&move)
+    pub fn compose(&self, other: &CubieCube) -> CubieCube {
+        let mut result = CubieCube::identity();
+        for i in 0..8 {
+            let from = other.cp[i] as usize;
+            result.cp[i] = self.cp[from];
+            result.co[i] = (self.co[from] + other.co[i]) % 3;
+        }
+        for i in 0..12 {
+            let from = other.ep[i] as usize;
+            result.ep[i] = self.ep[from];
+            result.eo[i] = (self.eo[from] + other.eo[i]) % 2;
+        }
+        result
+    }
+
+    // the state that undoes this one, self.compose(&self.inverse()) is the identity
+    pub fn inverse(&self) -> CubieCube {
+        let mut result = CubieCube::identity();
+        for i in 0..8 {
+            let corner = self.cp[i] as usize;
+            result.cp[corner] = Corner::iter().nth(i).unwrap();
+            result.co[corner] = (3 - self.co[i]) % 3;
+        }
+        for i in 0..12 {
+            let edge = self.ep[i] as usize;
+            result.ep[edge] = Edge::iter().nth(i).unwrap();
+            result.eo[edge] = self.eo[i];
+        }
+        result
+    }
+
+    // every corner and edge is in exactly one slot, and every twist and flip is in range
+    pub fn is_valid(&self) -> bool {
+        Corner::iter().all(|corner| self.cp.contains(&corner))
+            && Edge::iter().all(|edge| self.ep.contains(&edge))
+            && self.co.iter().all(|&twist| twist < 3)
+            && self.eo.iter().all(|&flip| flip < 2)
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == CubieCube::identity()
+    }
+
+    // the corner is in its own slot and not twisted
+    pub fn corner_solved(&self, corner: Corner) -> bool {
+        self.cp[corner as usize] == corner && self.co[corner as usize] == 0
+    }
+
+    // the edge is in its own slot and not flipped
+    pub fn edge_solved(&self, edge: Edge) -> bool {
+        self.ep[edge as usize] == edge && self.eo[edge as usize] == 0
+    }
+
+    // every piece outside of the layer on the face is solved
+    pub(crate) fn first_two_layers_solved(&self, last_layer: &Direction) -> bool {
+        Corner::iter()
+            .filter(|corner| !corner.faces().contains(last_layer))
+            .all(|corner| self.corner_solved(corner))
+            && Edge::iter()
+                .filter(|edge| !edge.faces().contains(last_layer))
+                .all(|edge| self.edge_solved(edge))
+    }
+}
+
+// whether the permutation takes an odd number of swaps, by counting its cycles
+pub(crate) fn is_odd_permutation(permutation: &[usize]) -> bool {
+    let mut seen = vec![false; permutation.len()];
+    let mut cycles = 0;
+    for start in 0..permutation.len() {
+        if !seen[start] {
+            cycles += 1;
+            let mut i = start;
+            while !seen[i] {
+                seen[i] = true;
+                i = permutation[i];
+            }
+        }
+    }
+    (permutation.len() - cycles) % 2 == 1
+}
+
+impl Default for CubieCube {
+    fn default() -> Self {
+        CubieCube::identity()
+    }
+}
+
+impl TryFrom<&Cube> for CubieCube {
+    type Error = CubieError;
+
+    fn try_from(cube: &Cube) -> Result<Self, Self::Error> {
+        if cube.size() != 3 {
+            return Err(CubieError::UnsupportedSize(cube.size()));
+        }
+
+        let mut cubie = CubieCube::identity();
+        for piece in cube.pieces.iter() {
+            let home = piece.get_home_grid_position();
+            let position = piece.get_grid_position();
+            let orientation = piece.get_orientation();
+
+            if let (Some(corner), Some(slot)) = (
+                Corner::from_grid_position(home),
+                Corner::from_grid_position(position),
+            ) {
+                cubie.cp[slot as usize] = corner;
+                cubie.co[slot as usize] = corner.twist_in(slot, &orientation);
+            } else if let (Some(edge), Some(slot)) = (
+                Edge::from_grid_position(home),
+                Edge::from_grid_position(position),
+            ) {
+                // an edge is flipped when its reference sticker isn't on the reference face of the slot
+                let reference = orientation * edge.faces()[0].grid_vector();
+                cubie.ep[slot as usize] = edge;
+                cubie.eo[slot as usize] = (slot.faces()[0].grid_vector() != reference) as u8;
+            } else if home != position {
+                return Err(CubieError::CentersMoved);
+            }
+        }
+
+        Ok(cubie)
+    }
+}
+
+// a 3x3 in the default colors with its pieces where the cubie cube has them, held the standard way
+// the cubie cube doesn't know the color scheme or whether the cube is a supercube, so neither comes back
+impl TryFrom<&CubieCube> for Cube {
+    type Error = CubieError;
+
+    fn try_from(cubie: &CubieCube) -> Result<Self, Self::Error> {
+        if !cubie.is_valid() {
+            return Err(CubieError::InvalidPieces);
+        }
+        let mut cube = Cube::new();
+        for piece in cube.pieces.iter_mut() {
+            let home = piece.get_home_grid_position();
+
+            if let Some(corner) = Corner::from_grid_position(home) {
+                let slot = cubie.cp.iter().position(|c| *c == corner).unwrap();
+                let orientation =
+                    corner.orientation_in(Corner::iter().nth(slot).unwrap(), cubie.co[slot]);
+                piece.set_orientation(orientation);
+            } else if let Some(edge) = Edge::from_grid_position(home) {
+                let slot = cubie.ep.iter().position(|e| *e == edge).unwrap();
+                let flip = cubie.eo[slot] as usize;
+                let from = edge.faces();
+                let to = Edge::iter().nth(slot).unwrap().faces();
+                let to_first = to[flip].grid_vector();
+                let to_second = to[1 - flip].grid_vector();
+                // the third axis follows from the other two, keeping this a proper rotation
+                let orientation: Matrix3<i32> = to_first * from[0].grid_vector().transpose()
+                    + to_second * from[1].grid_vector().transpose()
+                    + to_first.cross(&to_second)
+                        * from[0]
+                            .grid_vector()
+                            .cross(&from[1].grid_vector())
+                            .transpose();
+                piece.set_orientation(orientation);
+            }
+        }
+        Ok(cube)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scramble::{random_move_scramble, random_state, rng_from_seed, MoveSet};
+
+    #[test]
+    fn converts_to_a_cube_and_back() {
+        let mut rng = rng_from_seed(1);
+        for _ in 0..10 {
+            let cubie = random_state(&mut rng);
+            let cube = Cube::try_from(&cubie).unwrap();
+            assert_eq!(CubieCube::try_from(&cube).unwrap(), cubie);
+        }
+    }
+
+    #[test]
+    fn composes_like_the_cube_turns() {
+        let mut rng = rng_from_seed(2);
+        let moves = random_move_scramble(&mut rng, 30, &MoveSet::face_turns());
+        let mut cube = Cube::new();
+        let mut cubie = CubieCube::identity();
+        for cube_move in moves.iter() {
+            cube.apply_move(cube_move);
+            cubie = cubie.compose(&CubieCube::from_move(cube_move).unwrap());
+            assert_eq!(CubieCube::try_from(&cube).unwrap(), cubie);
+        }
+        assert_eq!(CubieCube::from_moves(&moves), Some(cubie));
+    }
+
+    #[test]
+    fn inverse_undoes_the_state() {
+        let mut rng = rng_from_seed(3);
+        for _ in 0..10 {
+            let cubie = random_state(&mut rng);
+            assert!(cubie.compose(&cubie.inverse()).is_identity());
+            assert!(cubie.inverse().compose(&cubie).is_identity());
+        }
+    }
+
+    #[test]
+    fn rejects_pieces_that_cant_be_placed() {
+        let mut cubie = CubieCube::identity();
+        cubie.cp[1] = Corner::URF;
+        assert_eq!(
+            Cube::try_from(&cubie).err(),
+            Some(CubieError::InvalidPieces)
+        );
+        let mut cubie = CubieCube::identity();
+        cubie.eo[0] = 2;
+        assert_eq!(
+            Cube::try_from(&cubie).err(),
+            Some(CubieError::InvalidPieces)
+        );
+        assert_eq!(
+            CubieCube::try_from(&Cube::with_size(4)),
+            Err(CubieError::UnsupportedSize(4))
+        );
+    }
+}
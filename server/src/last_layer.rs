@@ -0,0 +1,447 @@
+use crate::cfop::{face_oriented, CfopStep};
+use crate::cube::{invert, Cube, CubeMove, Direction};
+use crate::cubie::{CubieCube, CubieError};
+use crate::notation::{self, ParseError};
+use serde::Serialize;
+use std::fmt;
+
+// Recognizes the OLL or PLL case of the last layer and looks up algorithms for it
+// Cases are defined by algorithms rather than by drawings of the last layer: the state an algorithm solves is the
+// case, so a case is recognized by comparing states, allowing for turns of the top layer before and after.
+// The algorithms come from a text file bundled with the crate, and more can be added from other files.
+
+const BUNDLED_ALGORITHMS: &str = include_str!("../data/last_layer.algs");
+
+// turns of the top layer, starting with none
+const TOP_TURNS: [Option<CubeMove>; 4] = [
+    None,
+    Some(CubeMove::U),
+    Some(CubeMove::U2),
+    Some(CubeMove::UPrime),
+];
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum AlgorithmSet {
+    OLL, // orient the last layer
+    PLL, // permute the last layer once it is oriented
+}
+
+struct Algorithm {
+    moves: Vec<CubeMove>,
+    state: CubieCube, // the state the algorithm solves, what it does to a solved cube played backwards
+}
+
+struct Case {
+    set: AlgorithmSet,
+    name: String,
+    algorithms: Vec<Algorithm>,
+}
+
+// Why a line of an algorithm file was rejected, lines are 1-based
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AlgorithmError {
+    pub line: usize,
+    pub problem: AlgorithmProblem,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum AlgorithmProblem {
+    Format,               // not <OLL or PLL> <case name>: <moves>
+    Notation(ParseError), // the moves couldn't be read
+    NotLastLayer,         // the algorithm doesn't keep the first two layers solved
+    WrongSet,             // an OLL that leaves the last layer oriented, or a PLL that doesn't
+    // the algorithm doesn't solve the case it is listed under
+    WrongCase {
+        expected: String,      // the case it is listed under
+        found: Option<String>, // the case it solves, None when it isn't one of the known ones
+    },
+    DuplicateCase(String), // a new case that is the same as this one
+}
+
+impl fmt::Display for AlgorithmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.problem {
+            AlgorithmProblem::Format => write!(f, "expected <OLL or PLL> <case name>: <moves>"),
+            AlgorithmProblem::Notation(error) => write!(f, "{}", error.message),
+            AlgorithmProblem::NotLastLayer => {
+                write!(f, "the algorithm doesn't keep the first two layers solved")
+            }
+            AlgorithmProblem::WrongSet => write!(f, "the algorithm doesn't belong in its set"),
+            AlgorithmProblem::WrongCase { expected, found } => write!(
+                f,
+                "the algorithm should solve {}, found {}",
+                expected,
+                found.as_deref().unwrap_or("an unrecognized case")
+            ),
+            AlgorithmProblem::DuplicateCase(case) => write!(f, "the case is the same as {}", case),
+        }
+    }
+}
+
+// Reasons the last layer couldn't be recognized
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum RecognitionError {
+    UnsupportedCube(CubieError), // only 3x3 cubes have OLL and PLL cases
+    FirstTwoLayersUnsolved,      // the last layer has to be on top with the rest solved
+    UnknownCase,                 // none of the loaded algorithms solve it
+}
+
+impl fmt::Display for RecognitionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecognitionError::UnsupportedCube(_) => write!(f, "only 3x3 cubes can be recognized"),
+            RecognitionError::FirstTwoLayersUnsolved => {
+                write!(
+                    f,
+                    "the first two layers must be solved, with the last layer on top"
+                )
+            }
+            RecognitionError::UnknownCase => write!(f, "no loaded algorithm solves this case"),
+        }
+    }
+}
+
+// An algorithm for a recognized case, with the top layer turns to make before and after it
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecognizedAlgorithm {
+    pub pre_auf: Option<CubeMove>,
+    pub moves: Vec<CubeMove>,
+    pub post_auf: Option<CubeMove>, // always None for OLL, where the top layer only needs to end up oriented
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecognizedCase {
+    pub set: AlgorithmSet,
+    pub name: String,
+    pub algorithms: Vec<RecognizedAlgorithm>,
+}
+
+// What is left of the last layer, the case for OLL and PLL and the turn for AUF
+#[derive(Debug, Clone, PartialEq)]
+pub struct LastLayerRecognition {
+    pub step: CfopStep,
+    pub case: Option<RecognizedCase>,
+    pub auf: Option<CubeMove>,
+}
+
+fn top_turn(turn: &Option<CubeMove>) -> CubieCube {
+    turn.as_ref().map_or(CubieCube::identity(), |turn| {
+        CubieCube::from_move(turn).unwrap()
+    })
+}
+
+// the orientation of the top layer pieces, all an OLL algorithm cares about
+fn top_orientation(cubie: &CubieCube) -> ([u8; 4], [u8; 4]) {
+    (
+        cubie.co[..4].try_into().unwrap(),
+        cubie.eo[..4].try_into().unwrap(),
+    )
+}
+
+// the top layer turns around the algorithm that solve the state, if there are any
+fn auf_for(
+    set: AlgorithmSet,
+    state: &CubieCube,
+    algorithm: &Algorithm,
+) -> Option<(Option<CubeMove>, Option<CubeMove>)> {
+    for pre_auf in TOP_TURNS.iter() {
+        let turned = state.compose(&top_turn(pre_auf));
+        match set {
+            // the algorithm orients the state exactly when the state is oriented like the one it solves
+            AlgorithmSet::OLL => {
+                if top_orientation(&turned) == top_orientation(&algorithm.state) {
+                    return Some((pre_auf.clone(), None));
+                }
+            }
+            // pre_auf, moves and post_auf solve the state when it is undoing post_auf and then the algorithm
+            AlgorithmSet::PLL => {
+                for post_auf in TOP_TURNS.iter() {
+                    let undone = top_turn(&post_auf.as_ref().map(|turn| turn.inverse()));
+                    if turned == undone.compose(&algorithm.state) {
+                        return Some((pre_auf.clone(), post_auf.clone()));
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+// The algorithms known for each case
+pub struct AlgorithmDatabase {
+    cases: Vec<Case>,
+}
+
+impl AlgorithmDatabase {
+    // the 57 OLL and 21 PLL cases with the algorithms bundled with the crate
+    pub fn bundled() -> Self {
+        let mut database = AlgorithmDatabase { cases: vec![] };
+        database
+            .extend(BUNDLED_ALGORITHMS)
+            .expect("bundled algorithms are valid");
+        database
+    }
+
+    // add the algorithms in a file, a line at a time, stopping at the first line that is wrong
+    // lines are <OLL or PLL> <case name>: <moves>, and empty lines and // comments are skipped
+    pub fn extend(&mut self, text: &str) -> Result<(), AlgorithmError> {
+        for (index, line) in text.lines().enumerate() {
+            let error = |problem| AlgorithmError {
+                line: index + 1,
+                problem,
+            };
+            let line = line.split("//").next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (header, sequence) = line
+                .split_once(':')
+                .ok_or(error(AlgorithmProblem::Format))?;
+            let (set, name) = match header.trim().split_once(' ') {
+                Some(("OLL", name)) => (AlgorithmSet::OLL, name.trim()),
+                Some(("PLL", name)) => (AlgorithmSet::PLL, name.trim()),
+                _ => return Err(error(AlgorithmProblem::Format)),
+            };
+            let moves = notation::parse(sequence)
+                .map_err(|parse_error| error(AlgorithmProblem::Notation(parse_error)))?;
+
+            // playing the algorithm backwards on a solved cube gives the state it solves
+            let mut cube = Cube::new();
+            for cube_move in invert(&moves).iter() {
+                cube.apply_move(cube_move);
+            }
+            let state = CubieCube::relative_to_centers(&cube).unwrap();
+            if !state.first_two_layers_solved(&Direction::Up) {
+                return Err(error(AlgorithmProblem::NotLastLayer));
+            }
+            let oriented = face_oriented(&state, &Direction::Up);
+            let solved_up_to_auf = TOP_TURNS
+                .iter()
+                .any(|turn| state.compose(&top_turn(turn)).is_identity());
+            if (set == AlgorithmSet::OLL) == oriented || solved_up_to_auf {
+                return Err(error(AlgorithmProblem::WrongSet));
+            }
+
+            let algorithm = Algorithm { moves, state };
+            let same_case = self.cases.iter().position(|case| {
+                case.set == set && auf_for(set, &algorithm.state, &case.algorithms[0]).is_some()
+            });
+            let listed_case = self
+                .cases
+                .iter()
+                .position(|case| case.set == set && case.name == name);
+            match (listed_case, same_case) {
+                (Some(listed), Some(same)) if listed == same => {
+                    self.cases[listed].algorithms.push(algorithm)
+                }
+                (Some(listed), found) => {
+                    return Err(error(AlgorithmProblem::WrongCase {
+                        expected: self.cases[listed].name.clone(),
+                        found: found.map(|same| self.cases[same].name.clone()),
+                    }))
+                }
+                (None, Some(same)) => {
+                    return Err(error(AlgorithmProblem::DuplicateCase(
+                        self.cases[same].name.clone(),
+                    )))
+                }
+                (None, None) => self.cases.push(Case {
+                    set,
+                    name: name.to_string(),
+                    algorithms: vec![algorithm],
+                }),
+            }
+        }
+        Ok(())
+    }
+
+    // how many cases of the set are known
+    pub fn case_count(&self, set: AlgorithmSet) -> usize {
+        self.cases.iter().filter(|case| case.set == set).count()
+    }
+
+    // the OLL or PLL case of a 3x3 held with its last layer on top, as seen from its centers
+    pub fn recognize(&self, cube: &Cube) -> Result<LastLayerRecognition, RecognitionError> {
+        let state =
+            CubieCube::relative_to_centers(cube).map_err(RecognitionError::UnsupportedCube)?;
+        if !state.first_two_layers_solved(&Direction::Up) {
+            return Err(RecognitionError::FirstTwoLayersUnsolved);
+        }
+
+        if state.is_identity() {
+            return Ok(LastLayerRecognition {
+                step: CfopStep::Solved,
+                case: None,
+                auf: None,
+            });
+        }
+        if let Some(turn) = TOP_TURNS
+            .iter()
+            .find(|turn| state.compose(&top_turn(turn)).is_identity())
+        {
+            return Ok(LastLayerRecognition {
+                step: CfopStep::AUF,
+                case: None,
+                auf: turn.clone(),
+            });
+        }
+
+        let (step, set) = if face_oriented(&state, &Direction::Up) {
+            (CfopStep::PLL, AlgorithmSet::PLL)
+        } else {
+            (CfopStep::OLL, AlgorithmSet::OLL)
+        };
+        let case = self
+            .cases
+            .iter()
+            .filter(|case| case.set == set)
+            .find(|case| auf_for(set, &state, &case.algorithms[0]).is_some())
+            .ok_or(RecognitionError::UnknownCase)?;
+        let algorithms = case
+            .algorithms
+            .iter()
+            .map(|algorithm| {
+                let (pre_auf, post_auf) = auf_for(set, &state, algorithm).unwrap();
+                RecognizedAlgorithm {
+                    pre_auf,
+                    moves: algorithm.moves.clone(),
+                    post_auf,
+                }
+            })
+            .collect();
+        Ok(LastLayerRecognition {
+            step,
+            case: Some(RecognizedCase {
+                set,
+                name: case.name.clone(),
+                algorithms,
+            }),
+            auf: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn played(cube: &mut Cube, moves: impl IntoIterator<Item = CubeMove>) {
+        for cube_move in moves {
+            cube.apply_move(&cube_move);
+        }
+    }
+
+    #[test]
+    fn bundles_every_case() {
+        let database = AlgorithmDatabase::bundled();
+        assert_eq!(database.case_count(AlgorithmSet::OLL), 57);
+        assert_eq!(database.case_count(AlgorithmSet::PLL), 21);
+    }
+
+    #[test]
+    fn recognizes_every_case_from_any_side() {
+        let database = AlgorithmDatabase::bundled();
+        for case in database.cases.iter() {
+            for turn in TOP_TURNS.iter() {
+                let mut cube =
+                    Cube::try_from(&case.algorithms[0].state.compose(&top_turn(turn))).unwrap();
+                let recognized = database.recognize(&cube).unwrap();
+                let recognized_case = recognized.case.unwrap();
+                assert_eq!(recognized_case.name, case.name);
+                let algorithm = &recognized_case.algorithms[0];
+                played(
+                    &mut cube,
+                    algorithm
+                        .pre_auf
+                        .iter()
+                        .chain(algorithm.moves.iter())
+                        .chain(algorithm.post_auf.iter())
+                        .cloned(),
+                );
+                let state = CubieCube::relative_to_centers(&cube).unwrap();
+                match case.set {
+                    AlgorithmSet::OLL => assert!(face_oriented(&state, &Direction::Up)),
+                    AlgorithmSet::PLL => assert!(state.is_identity()),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn recognizes_a_last_layer_that_only_needs_a_turn() {
+        let database = AlgorithmDatabase::bundled();
+        let mut cube = Cube::new();
+        assert_eq!(database.recognize(&cube).unwrap().step, CfopStep::Solved);
+        played(&mut cube, [CubeMove::U]);
+        let recognition = database.recognize(&cube).unwrap();
+        assert_eq!(recognition.step, CfopStep::AUF);
+        assert_eq!(recognition.auf, Some(CubeMove::UPrime));
+        played(&mut cube, [CubeMove::R]);
+        assert_eq!(
+            database.recognize(&cube),
+            Err(RecognitionError::FirstTwoLayersUnsolved)
+        );
+    }
+
+    #[test]
+    fn rejects_algorithms_that_dont_fit_their_case() {
+        let mut database = AlgorithmDatabase::bundled();
+        let problem = |database: &mut AlgorithmDatabase, text| database.extend(text).unwrap_err();
+        assert_eq!(
+            problem(&mut database, "PLL T: R U R' U'"),
+            AlgorithmError {
+                line: 1,
+                problem: AlgorithmProblem::NotLastLayer
+            }
+        );
+        assert_eq!(
+            problem(
+                &mut database,
+                "// T perm\nOLL T: R U R' U' R' F R2 U' R' U' R U R' F'"
+            )
+            .problem,
+            AlgorithmProblem::WrongSet
+        );
+        assert_eq!(
+            problem(
+                &mut database,
+                "PLL T2: R U R' U' R' F R2 U' R' U' R U R' F'"
+            )
+            .problem,
+            AlgorithmProblem::DuplicateCase("T".to_string())
+        );
+        assert_eq!(
+            problem(&mut database, "PLL H: R U R' U' R' F R2 U' R' U' R U R' F'").problem,
+            AlgorithmProblem::WrongCase {
+                expected: "H".to_string(),
+                found: Some("T".to_string()),
+            }
+        );
+        assert_eq!(
+            problem(&mut database, "PLL T R U R'").problem,
+            AlgorithmProblem::Format
+        );
+
+        // a case the database doesn't know yet
+        let mut database = AlgorithmDatabase { cases: vec![] };
+        let error = problem(
+            &mut database,
+            "PLL T: R U R' U' R' F R2 U' R' U' R U R' F'\nPLL T: M2 U M2 U2 M2 U M2",
+        );
+        assert_eq!(error.line, 2);
+        assert_eq!(
+            error.problem,
+            AlgorithmProblem::WrongCase {
+                expected: "T".to_string(),
+                found: None,
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "line 2: the algorithm should solve T, found an unrecognized case"
+        );
+    }
+}
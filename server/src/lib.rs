@@ -0,0 +1,14 @@
+// Cube model and tooling, shared by the web server in main.rs and any other tools that want to use it directly
+pub mod beginner;
+pub mod cfop;
+pub mod cube;
+pub mod cubie;
+pub mod last_layer;
+pub mod notation;
+pub mod optimal;
+pub mod pattern;
+pub mod pocket;
+pub mod scramble;
+pub mod supercube;
+pub mod two_phase;
+pub mod validation;
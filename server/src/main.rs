@@ -1,71 +1,768 @@
 #[macro_use]
 extern crate rocket;
+use rocket::response::status::BadRequest;
+use rocket::serde::{json::Json, Serialize};
 use rocket::State;
-use rocket::serde::{json::Json, Deserialize, Serialize};
-use std::sync::atomic::AtomicUsize;
-use std::sync::{Arc, Mutex};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
 
 mod cors;
 
-mod cube;
-use cube::Cube;
-use cube::CubeMove;
-use cube::CubePiece;
-use cube::StickerColor;
+use rubiks_cube::beginner::{self, SolveStep};
+use rubiks_cube::cfop::{self, CfopAnalysis, CfopStep};
+use rubiks_cube::cube::CubeMove;
+use rubiks_cube::cube::CubePiece;
+use rubiks_cube::cube::FaceletError;
+use rubiks_cube::cube::StickerColor;
+use rubiks_cube::cube::StickerId;
+use rubiks_cube::cube::{ColorScheme, Cube, Easing};
+use rubiks_cube::cube::{Metric, MoveCounts};
+use rubiks_cube::cube::{MAX_CUBE_SIZE, MIN_CUBE_SIZE};
+use rubiks_cube::last_layer::{AlgorithmDatabase, AlgorithmSet};
+use rubiks_cube::notation::{self, ParseError};
+use rubiks_cube::optimal::{self, PatternDatabases};
+use rubiks_cube::pattern::{self, Pattern};
+use rubiks_cube::pocket;
+use rubiks_cube::scramble::{
+    random_big_cube_scramble, random_move_scramble, random_pocket_state_scramble,
+    random_state_scramble, random_supercube_state_scramble, rng_from_seed, MoveSet,
+    MAX_RANDOM_MOVES,
+};
+use rubiks_cube::supercube;
+use rubiks_cube::two_phase;
+use rubiks_cube::validation::{self, ValidationError};
 
 struct AppState {
     cube: Cube,
+    history: Vec<CubeMove>, // every move played on the cube since it was last replaced
+    revision: u64, // goes up whenever the cube changes, so a solve can tell the cube it solved is still there
+    optimal_search: Option<OptimalSearch>,
 }
 
 type AppStatePointer = Arc<Mutex<AppState>>;
 
 impl AppState {
     fn new() -> AppStatePointer {
-        let app_state = AppState { cube: Cube::new() };
+        let app_state = AppState {
+            cube: Cube::new(),
+            history: vec![],
+            revision: 0,
+            optimal_search: None,
+        };
         Arc::new(Mutex::new(app_state))
     }
+
+    // play moves on the session cube and remember them in the history
+    fn play(&mut self, moves: &[CubeMove], options: &AnimationOptions) -> Animation {
+        self.history.extend_from_slice(moves);
+        self.revision += 1;
+        apply_moves_with_animation(&mut self.cube, moves, options)
+    }
+
+    // start over with another cube and an empty history
+    fn replace_cube(&mut self, cube: Cube) {
+        self.cube = cube;
+        self.history.clear();
+        self.revision += 1;
+    }
+}
+
+// how moves played on the session cube are animated, from the frames, easing and move_ms query parameters
+// every move takes move_ms (500 by default, up to 5000) split into frames steps (10 by default), and turns as fast
+// as easing (linear by default, ease-in-out or cubic) says
+#[derive(FromForm)]
+struct AnimationOptions {
+    #[field(default = 10, validate = range(1..=100))]
+    frames: usize,
+    #[field(default = Easing::Linear)]
+    easing: Easing,
+    #[field(default = 500, validate = range(1..=5000))]
+    move_ms: u64,
+}
+
+// the most frames an animation holds, each is a copy of every piece
+// longer sequences get fewer steps a move, and once that would be less than one they aren't animated at all
+const MAX_ANIMATION_FRAMES: usize = 1000;
+
+// frames of the pieces part of the way through the moves and when to show each of them
+#[derive(Default)]
+struct Animation {
+    frames: Vec<Vec<CubePiece>>,
+    times_ms: Vec<f64>, // from the start of the first move
+    duration_ms: f64,   // when the last move is done and the pieces of the cube itself are shown
 }
 
 #[derive(Serialize)]
 #[serde(crate = "rocket::serde")]
 struct CubeState {
+    size: usize,
     pieces: Vec<CubePiece>,
     animation: Vec<Vec<CubePiece>>,
+    animation_times_ms: Vec<f64>, // when to show each frame of animation, in milliseconds from the start
+    animation_duration_ms: f64,   // when to show pieces, after the last frame
     faces: Vec<Vec<Vec<StickerColor>>>,
+    centers: Vec<StickerColor>,
+    facelets: String,
+    sticker_ids: Vec<StickerId>, // the slot each sticker of facelets started in, like URF-U
+    orientations: Vec<Vec<Vec<u8>>>, // quarter turns clockwise of each sticker in faces, what a supercube shows on its centers
+    supercube: bool,
+    solved: bool, // solved in any orientation, so whole cube rotations don't count against it
 }
 
+impl CubeState {
+    fn new(cube: &Cube, animation: Animation) -> Self {
+        CubeState {
+            size: cube.size(),
+            pieces: cube.pieces.clone(),
+            animation: animation.frames,
+            animation_times_ms: animation.times_ms,
+            animation_duration_ms: animation.duration_ms,
+            faces: cube.unwrap(),
+            centers: cube.centers(),
+            facelets: cube.to_facelets(),
+            sticker_ids: cube.sticker_ids(),
+            orientations: cube.unwrap_orientations(),
+            supercube: cube.is_supercube(),
+            solved: cube.is_solved_up_to_rotation(),
+        }
+    }
+}
 
 #[get("/get_cube", format = "json")]
 fn get_cube(app_state_pointer: &State<AppStatePointer>) -> Json<CubeState> {
     let app_state = app_state_pointer.lock().unwrap();
     let cube = &app_state.cube;
     cube.print();
-    Json(CubeState {
-        pieces: cube.pieces.clone(),
-        animation: vec![],
-        faces: cube.unwrap(),
-    })
+    Json(CubeState::new(cube, Animation::default()))
+}
+
+#[post("/apply_move/<cube_move>?<animation..>")]
+fn apply_move(
+    app_state: &State<AppStatePointer>,
+    cube_move: CubeMove,
+    animation: AnimationOptions,
+) -> Result<Json<CubeState>, BadRequest<String>> {
+    let mut app_state = app_state.lock().unwrap();
+    let size = app_state.cube.size();
+    if !cube_move.fits(size) {
+        return Err(BadRequest(format!(
+            "{} turns a layer a {}x{} cube doesn't have",
+            cube_move, size, size
+        )));
+    }
+    let animation = app_state.play(&[cube_move], &animation);
+    let cube = &app_state.cube;
+    cube.print();
+    Ok(Json(CubeState::new(cube, animation)))
+}
+
+// apply a whole sequence in standard notation, sent as the request body, for example "R U R' U' [R, U]"
+#[post("/apply_moves?<animation..>", data = "<sequence>")]
+fn apply_moves(
+    app_state: &State<AppStatePointer>,
+    sequence: &str,
+    animation: AnimationOptions,
+) -> Result<Json<CubeState>, BadRequest<Json<ParseError>>> {
+    let mut app_state = app_state.lock().unwrap();
+    let moves = notation::parse_for_size(sequence, app_state.cube.size())
+        .map_err(|error| BadRequest(Json(error)))?;
+
+    let animation = app_state.play(&moves, &animation);
+    let cube = &app_state.cube;
+    cube.print();
+    Ok(Json(CubeState::new(cube, animation)))
+}
+
+// play the moves back to back, with the state after each move in between
+fn apply_moves_with_animation(
+    cube: &mut Cube,
+    moves: &[CubeMove],
+    options: &AnimationOptions,
+) -> Animation {
+    // a move takes as many frames as it has steps, counting the state after it
+    let steps = options
+        .frames
+        .min(MAX_ANIMATION_FRAMES / moves.len().max(1));
+    if steps == 0 {
+        for cube_move in moves {
+            cube.apply_move(cube_move);
+        }
+        return Animation::default();
+    }
+    let mut animation = Animation::default();
+    let move_ms = options.move_ms as f64;
+    let frame_ms = move_ms / steps as f64;
+    for (index, cube_move) in moves.iter().enumerate() {
+        let start_ms = index as f64 * move_ms;
+        let frames = cube.apply_move_with_animation(cube_move, steps, options.easing);
+        animation
+            .times_ms
+            .extend((1..=frames.len()).map(|frame| start_ms + frame as f64 * frame_ms));
+        animation.frames.extend(frames);
+        if index + 1 < moves.len() {
+            animation.frames.push(cube.pieces.clone());
+            animation.times_ms.push(start_ms + move_ms);
+        }
+    }
+    animation.duration_ms = moves.len() as f64 * move_ms;
+    animation
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct ScrambleResponse {
+    scramble: String,
+    seed: u64,               // pass it back to get the same scramble again
+    cube: Option<CubeState>, // the session cube, when the scramble was applied to it
+}
+
+// why a scramble couldn't be made
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+enum InvalidScramble {
+    Moves(ParseError),                     // the moves to pick from couldn't be read
+    TooLong { length: usize, max: usize }, // more random moves were asked for than a scramble can have
+}
+
+// random state scramble, for a 2x2 when the session cube is one and a 3x3 otherwise
+// with the centers turned a random way too when the session cube is a 3x3 supercube
+// and random block turns as long as a WCA scramble when the session cube is bigger
+// with a length a random move scramble for any size instead
+// random move scrambles can be limited to the turns of some moves, moves=R U gives <R, U>, and can leave out double turns
+// apply=true also plays the scramble on the session cube
+#[post("/scramble?<seed>&<apply>&<length>&<moves>&<double_turns>&<animation..>")]
+fn scramble(
+    app_state: &State<AppStatePointer>,
+    seed: Option<u64>,
+    apply: Option<bool>,
+    length: Option<usize>,
+    moves: Option<&str>,
+    double_turns: Option<bool>,
+    animation: AnimationOptions,
+) -> Result<Json<ScrambleResponse>, BadRequest<Json<InvalidScramble>>> {
+    // new seeds stay small enough to survive a round trip through a JavaScript number
+    let seed = seed.unwrap_or_else(|| rand::random::<u32>() as u64);
+    let mut rng = rng_from_seed(seed);
+    // the lock is only held to look at the cube, not while a random state is being solved
+    let (size, supercube) = {
+        let cube = &app_state.lock().unwrap().cube;
+        (cube.size(), cube.is_supercube())
+    };
+    let moves = match length {
+        Some(length) => {
+            if length > MAX_RANDOM_MOVES {
+                return Err(BadRequest(Json(InvalidScramble::TooLong {
+                    length,
+                    max: MAX_RANDOM_MOVES,
+                })));
+            }
+            let mut move_set = match moves {
+                Some(generators) => MoveSet::generated_by(
+                    &notation::parse_for_size(generators, size)
+                        .map_err(|error| BadRequest(Json(InvalidScramble::Moves(error))))?,
+                ),
+                None => MoveSet::face_turns(),
+            };
+            if !double_turns.unwrap_or(true) {
+                move_set = move_set.without_double_turns();
+            }
+            random_move_scramble(&mut rng, length, &move_set)
+        }
+        None if size == 2 => random_pocket_state_scramble(&mut rng),
+        None if size > 3 => random_big_cube_scramble(&mut rng, size),
+        None if supercube => random_supercube_state_scramble(&mut rng),
+        None => random_state_scramble(&mut rng),
+    };
+
+    let cube = apply.unwrap_or(false).then(|| {
+        let mut app_state = app_state.lock().unwrap();
+        let animation = app_state.play(&moves, &animation);
+        let cube = &app_state.cube;
+        cube.print();
+        CubeState::new(cube, animation)
+    });
+    Ok(Json(ScrambleResponse {
+        scramble: notation::format(&moves),
+        seed,
+        cube,
+    }))
 }
 
-#[post("/apply_move/<cube_move>")]
-fn apply_move(app_state: &State<AppStatePointer>, cube_move: CubeMove) -> Json<CubeState> {
+// replace the session cube with a new solved cube, defaults to a 3x3
+// scheme is western (the default), japanese, or six colors for the U, R, F, D, L and B faces separated by commas,
+// each a name like white or any color as #rrggbb
+// supercube=true makes a cube whose centers have to be turned the right way to be solved
+#[post("/new_cube?<size>&<scheme>&<supercube>")]
+fn new_cube(
+    app_state: &State<AppStatePointer>,
+    size: Option<usize>,
+    scheme: Option<&str>,
+    supercube: Option<bool>,
+) -> Result<Json<CubeState>, BadRequest<String>> {
+    let size = size.unwrap_or(3);
+    if !(MIN_CUBE_SIZE..=MAX_CUBE_SIZE).contains(&size) {
+        return Err(BadRequest(format!(
+            "cube size must be between {} and {}",
+            MIN_CUBE_SIZE, MAX_CUBE_SIZE
+        )));
+    }
+    let scheme = match scheme {
+        Some(text) => ColorScheme::parse(text)
+            .ok_or_else(|| BadRequest(format!("unknown color scheme '{}'", text)))?,
+        None => ColorScheme::default(),
+    };
+
     let mut app_state = app_state.lock().unwrap();
-    let mut cube = &mut app_state.cube;
+    let mut cube = Cube::with_scheme(size, scheme);
+    if supercube.unwrap_or(false) {
+        cube = cube.into_supercube();
+    }
+    app_state.replace_cube(cube);
+    let cube = &app_state.cube;
+    cube.print();
+    Ok(Json(CubeState::new(cube, Animation::default())))
+}
 
-    let animation = cube.clone().apply_move_with_animation(&cube_move, 10);
-    cube.apply_move(&cube_move);
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct SolveStepResponse {
+    name: &'static str,
+    explanation: &'static str,
+    moves: String,
+    counts: MoveCounts,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct SolveResponse {
+    steps: Vec<SolveStepResponse>,
+    counts: MoveCounts, // of the whole solution
+    cube: CubeState,    // the session cube after solving, animated through every step
+}
+
+// solve the session cube and play the solution on it
+// method=beginner solves layer by layer in named steps, method=kociemba finds a short solution with the two-phase
+// algorithm, looking for shorter ones for timeout_ms (1000 by default) and never using more than max_length moves
+// a 2x2 is always solved optimally, the method is ignored
+// pattern, a masked facelet string, solves a 3x3 only as far as the pattern in the fewest moves instead, trying up
+// to max_length moves (12 by default) for timeout_ms (10000 by default)
+// metric (htm by default, or qtm, stm or etm) is what kociemba, the pattern and the 2x2 solver count moves in,
+// max_length included which defaults to 40 quarter turns for kociemba
+// a 3x3 supercube gets a last step that turns the centers back, except when solving a pattern
+// the solution is only played when the cube hasn't changed while it was being found
+#[post("/solve?<method>&<max_length>&<timeout_ms>&<metric>&<pattern>&<animation..>")]
+fn solve(
+    app_state: &State<AppStatePointer>,
+    method: Option<&str>,
+    max_length: Option<usize>,
+    timeout_ms: Option<u64>,
+    metric: Option<&str>,
+    pattern: Option<&str>,
+    animation: AnimationOptions,
+) -> Result<Json<SolveResponse>, BadRequest<String>> {
+    let metric = parse_metric(metric)?;
+    let pattern = pattern
+        .map(Pattern::from_facelets)
+        .transpose()
+        .map_err(|error| BadRequest(format!("invalid pattern: {}", error)))?;
+    // the lock is only held to copy the cube, not while it is being solved
+    let (cube, revision) = {
+        let app_state = app_state.lock().unwrap();
+        (app_state.cube.clone(), app_state.revision)
+    };
+    let cube = &cube;
+
+    let mut steps = match method.unwrap_or("beginner") {
+        _ if pattern.is_some() => {
+            let timeout = Duration::from_millis(timeout_ms.unwrap_or(10000));
+            let moves = pattern::solve(
+                cube,
+                pattern.as_ref().unwrap(),
+                max_length.unwrap_or(12),
+                metric,
+                Some(timeout),
+            )
+            .map_err(|error| BadRequest(error.to_string()))?;
+            vec![SolveStep {
+                name: "Pattern",
+                explanation: "Bring the stickers the pattern asks for into place in the fewest possible moves.",
+                moves,
+            }]
+        }
+        _ if cube.size() == 2 => vec![SolveStep {
+            name: "Optimal",
+            explanation:
+                "Solve the 2x2 in the fewest possible moves, read from a table of every state.",
+            moves: pocket::solve(cube, metric).map_err(|error| BadRequest(error.to_string()))?,
+        }],
+        "beginner" => beginner::solve(cube).map_err(|error| BadRequest(error.to_string()))?,
+        "kociemba" => {
+            let timeout = Duration::from_millis(timeout_ms.unwrap_or(1000));
+            let default_max_length = if metric == Metric::Qtm { 40 } else { 30 };
+            let moves = two_phase::solve(
+                cube,
+                max_length.unwrap_or(default_max_length),
+                metric,
+                Some(timeout),
+            )
+            .map_err(|error| BadRequest(error.to_string()))?;
+            vec![SolveStep {
+                name: "Two-phase",
+                explanation: "Orient every piece and bring the middle edges into the middle layer, then solve the rest with U, D and half turns.",
+                moves,
+            }]
+        }
+        method => return Err(BadRequest(format!("unknown method '{}'", method))),
+    };
+    if cube.is_supercube() && cube.size() == 3 && pattern.is_none() {
+        let mut pieces_solved = cube.clone();
+        for cube_move in steps.iter().flat_map(|step| step.moves.iter()) {
+            pieces_solved.apply_move(cube_move);
+        }
+        steps.push(SolveStep {
+            name: "Centers",
+            explanation: "Turn the centers back the way they started with algorithms that move no other piece.",
+            moves: supercube::solve_centers(&pieces_solved)
+                .map_err(|error| BadRequest(error.to_string()))?,
+        });
+    }
+
+    let moves: Vec<CubeMove> = steps.iter().flat_map(|step| step.moves.clone()).collect();
+    let mut app_state = app_state.lock().unwrap();
+    if app_state.revision != revision {
+        return Err(BadRequest(
+            "the cube changed while it was being solved".to_string(),
+        ));
+    }
+    let animation = app_state.play(&moves, &animation);
+    let cube = &app_state.cube;
     cube.print();
-    Json(CubeState {
-        pieces: cube.pieces.clone(),
-        animation,
-        faces: cube.unwrap(),
+    Ok(Json(SolveResponse {
+        steps: steps
+            .iter()
+            .map(|step| SolveStepResponse {
+                name: step.name,
+                explanation: step.explanation,
+                moves: notation::format(&step.moves),
+                counts: MoveCounts::of(&step.moves, cube.size()),
+            })
+            .collect(),
+        counts: MoveCounts::of(&moves, cube.size()),
+        cube: CubeState::new(cube, animation),
+    }))
+}
+
+// where an optimal search running in the background is at
+#[derive(Serialize, Clone)]
+#[serde(crate = "rocket::serde")]
+enum OptimalSearchStatus {
+    LoadingTables,
+    GeneratingTables {
+        database: String,
+        depth: usize,
+        states: usize,
+    },
+    Searching {
+        depth: usize,
+        nodes: u64,
+    },
+    Solved {
+        moves: String,
+        counts: MoveCounts,
+    },
+    Cancelled,
+    Failed(String),
+}
+
+struct OptimalSearch {
+    status: Arc<Mutex<OptimalSearchStatus>>,
+    cancel: Arc<AtomicBool>,
+}
+
+// loaded by the first optimal search and kept for the life of the process
+static PATTERN_DATABASES: OnceLock<PatternDatabases> = OnceLock::new();
+const PATTERN_DATABASE_DIRECTORY: &str = "pattern_databases";
+
+// start looking for an optimal solution of the session cube, replacing any search still running
+// the cube isn't changed, the solution is read from GET /optimal_solve once the status is Solved
+// metric is htm by default, qtm finds the fewest quarter turns instead, stm and etm count the same as htm here
+#[post("/optimal_solve?<metric>")]
+fn start_optimal_solve(
+    app_state: &State<AppStatePointer>,
+    metric: Option<&str>,
+) -> Result<Json<OptimalSearchStatus>, BadRequest<String>> {
+    let metric = parse_metric(metric)?;
+    let mut app_state = app_state.lock().unwrap();
+    if let Some(search) = &app_state.optimal_search {
+        search.cancel.store(true, Ordering::Relaxed);
+    }
+
+    let status = Arc::new(Mutex::new(OptimalSearchStatus::LoadingTables));
+    let cancel = Arc::new(AtomicBool::new(false));
+    let cube = app_state.cube.clone();
+    let (thread_status, thread_cancel) = (status.clone(), cancel.clone());
+    thread::spawn(move || {
+        let databases = match PATTERN_DATABASES.get() {
+            Some(databases) => Some(databases),
+            None => PatternDatabases::load_or_generate(
+                Path::new(PATTERN_DATABASE_DIRECTORY),
+                &thread_cancel,
+                |progress| {
+                    *thread_status.lock().unwrap() = OptimalSearchStatus::GeneratingTables {
+                        database: progress.database.clone(),
+                        depth: progress.depth,
+                        states: progress.states,
+                    };
+                },
+            )
+            // a search started meanwhile may have finished loading them first
+            .map(|databases| PATTERN_DATABASES.get_or_init(|| databases)),
+        };
+        let Some(databases) = databases else {
+            *thread_status.lock().unwrap() = OptimalSearchStatus::Cancelled;
+            return;
+        };
+        let result = optimal::solve(&cube, databases, metric, &thread_cancel, |progress| {
+            *thread_status.lock().unwrap() = OptimalSearchStatus::Searching {
+                depth: progress.depth,
+                nodes: progress.nodes,
+            };
+        });
+        *thread_status.lock().unwrap() = match result {
+            Ok(moves) => OptimalSearchStatus::Solved {
+                moves: notation::format(&moves),
+                counts: MoveCounts::of(&moves, 3),
+            },
+            Err(two_phase::SolveError::Cancelled) => OptimalSearchStatus::Cancelled,
+            Err(error) => OptimalSearchStatus::Failed(error.to_string()),
+        };
+    });
+
+    let current = status.lock().unwrap().clone();
+    app_state.optimal_search = Some(OptimalSearch { status, cancel });
+    Ok(Json(current))
+}
+
+#[get("/optimal_solve")]
+fn optimal_solve_status(
+    app_state: &State<AppStatePointer>,
+) -> Result<Json<OptimalSearchStatus>, BadRequest<String>> {
+    let app_state = app_state.lock().unwrap();
+    let search = app_state
+        .optimal_search
+        .as_ref()
+        .ok_or_else(|| BadRequest("no optimal search was started".to_string()))?;
+    let status = search.status.lock().unwrap().clone();
+    Ok(Json(status))
+}
+
+// stop the optimal search, it finishes within a moment and its status becomes Cancelled
+#[delete("/optimal_solve")]
+fn cancel_optimal_solve(
+    app_state: &State<AppStatePointer>,
+) -> Result<Json<OptimalSearchStatus>, BadRequest<String>> {
+    let app_state = app_state.lock().unwrap();
+    let search = app_state
+        .optimal_search
+        .as_ref()
+        .ok_or_else(|| BadRequest("no optimal search was started".to_string()))?;
+    search.cancel.store(true, Ordering::Relaxed);
+    let status = search.status.lock().unwrap().clone();
+    Ok(Json(status))
+}
+
+// how many 2x2 states are each number of moves away from solved in the metric, the distance is the index
+#[get("/pocket_distances?<metric>")]
+fn pocket_distances(metric: Option<&str>) -> Result<Json<Vec<usize>>, BadRequest<String>> {
+    let metric = parse_metric(metric)?;
+    Ok(Json(pocket::distance_histogram(metric)))
+}
+
+// a metric given by its abbreviation, the half turn metric when there is none
+fn parse_metric(metric: Option<&str>) -> Result<Metric, BadRequest<String>> {
+    match metric {
+        Some(name) => {
+            Metric::from_name(name).ok_or_else(|| BadRequest(format!("unknown metric '{}'", name)))
+        }
+        None => Ok(Metric::Htm),
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct HistoryResponse {
+    moves: String,
+    counts: MoveCounts,
+}
+
+// every move played on the session cube since it was last replaced by new_cube or set_facelets
+#[get("/history")]
+fn history(app_state: &State<AppStatePointer>) -> Json<HistoryResponse> {
+    let app_state = app_state.lock().unwrap();
+    Json(HistoryResponse {
+        moves: notation::format(&app_state.history),
+        counts: MoveCounts::of(&app_state.history, app_state.cube.size()),
     })
 }
 
+// how far the session cube is through CFOP for every cross color, furthest along first
+#[get("/analyze")]
+fn analyze(app_state: &State<AppStatePointer>) -> Result<Json<CfopAnalysis>, BadRequest<String>> {
+    let app_state = app_state.lock().unwrap();
+    cfop::analyze(&app_state.cube)
+        .map(Json)
+        .map_err(|_| BadRequest("only 3x3 cubes can be analyzed".to_string()))
+}
+
+// algorithms users add to the bundled ones, read from the working directory when the server starts
+const USER_ALGORITHM_FILE: &str = "last_layer.algs";
+
+fn load_algorithms() -> AlgorithmDatabase {
+    let mut database = AlgorithmDatabase::bundled();
+    if let Ok(text) = std::fs::read_to_string(USER_ALGORITHM_FILE) {
+        // lines before a bad one are kept, so fixing it doesn't lose them in the meantime
+        if let Err(error) = database.extend(&text) {
+            eprintln!("{}: {}", USER_ALGORITHM_FILE, error);
+        }
+    }
+    database
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct AlgorithmResponse {
+    pre_auf: Option<String>,
+    moves: String,
+    post_auf: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct CaseResponse {
+    set: AlgorithmSet,
+    name: String,
+    algorithms: Vec<AlgorithmResponse>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct RecognizeResponse {
+    step: CfopStep,
+    case: Option<CaseResponse>,
+    auf: Option<String>, // the turn that finishes the cube, for the AUF step
+}
+
+// the OLL or PLL case of the session cube and the algorithms for it, the cube held with the last layer on top
+#[get("/recognize")]
+fn recognize(
+    app_state: &State<AppStatePointer>,
+    algorithms: &State<AlgorithmDatabase>,
+) -> Result<Json<RecognizeResponse>, BadRequest<String>> {
+    let app_state = app_state.lock().unwrap();
+    let recognition = algorithms
+        .recognize(&app_state.cube)
+        .map_err(|error| BadRequest(error.to_string()))?;
+    let format_turn = |turn: Option<CubeMove>| turn.map(|turn| notation::format(&[turn]));
+    Ok(Json(RecognizeResponse {
+        step: recognition.step,
+        case: recognition.case.map(|case| CaseResponse {
+            set: case.set,
+            name: case.name,
+            algorithms: case
+                .algorithms
+                .into_iter()
+                .map(|algorithm| AlgorithmResponse {
+                    pre_auf: format_turn(algorithm.pre_auf),
+                    moves: notation::format(&algorithm.moves),
+                    post_auf: format_turn(algorithm.post_auf),
+                })
+                .collect(),
+        }),
+        auf: format_turn(recognition.auf),
+    }))
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct MatchResponse {
+    matches: bool,
+    matches_up_to_auf: bool, // after turning the U layer
+}
+
+// compare the session cube with a pattern sent as the request body, a facelet string with X for stickers that don't matter
+#[post("/match", data = "<pattern>")]
+fn match_pattern(
+    app_state: &State<AppStatePointer>,
+    pattern: &str,
+) -> Result<Json<MatchResponse>, BadRequest<Json<FaceletError>>> {
+    let pattern = Pattern::from_facelets(pattern).map_err(|error| BadRequest(Json(error)))?;
+    let app_state = app_state.lock().unwrap();
+    Ok(Json(MatchResponse {
+        matches: pattern.matches(&app_state.cube),
+        matches_up_to_auf: pattern.matches_up_to_auf(&app_state.cube),
+    }))
+}
+
+// why a cube state sent to the server was rejected
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+enum InvalidCube {
+    Facelets(FaceletError),           // the input couldn't be read
+    Unsolvable(Vec<ValidationError>), // the input was read but no real cube can be in that state
+}
+
+// replace the session cube with the state in a facelet string, as exported in the facelets field
+// the new cube keeps the color scheme of the one it replaces and stays a supercube if it was one,
+// with every center turned the way it started since facelets can't show how centers are turned,
+// except for a quarter turn of the U center when the pieces can't be reached without one
+#[post("/set_facelets", data = "<facelets>")]
+fn set_facelets(
+    app_state: &State<AppStatePointer>,
+    facelets: &str,
+) -> Result<Json<CubeState>, BadRequest<Json<InvalidCube>>> {
+    let mut app_state = app_state.lock().unwrap();
+    let scheme = app_state.cube.color_scheme().clone();
+    let mut cube = Cube::from_facelets_with_scheme(facelets, scheme)
+        .map_err(|error| BadRequest(Json(InvalidCube::Facelets(error))))?;
+    if app_state.cube.is_supercube() {
+        cube = supercube::from_pieces(cube);
+    }
+    validation::validate(&cube)
+        .map_err(|errors| BadRequest(Json(InvalidCube::Unsolvable(errors))))?;
+
+    app_state.replace_cube(cube);
+    let cube = &app_state.cube;
+    cube.print();
+    Ok(Json(CubeState::new(cube, Animation::default())))
+}
+
 #[launch]
 fn rocket() -> _ {
     rocket::build()
         .attach(cors::Cors)
-        .mount("/", routes![get_cube, apply_move])
+        .mount(
+            "/",
+            routes![
+                get_cube,
+                apply_move,
+                apply_moves,
+                new_cube,
+                set_facelets,
+                scramble,
+                solve,
+                start_optimal_solve,
+                optimal_solve_status,
+                cancel_optimal_solve,
+                pocket_distances,
+                history,
+                analyze,
+                recognize,
+                match_pattern
+            ],
+        )
         .manage(AppState::new())
+        .manage(load_algorithms())
 }
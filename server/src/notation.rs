@@ -0,0 +1,366 @@
+use crate::cube::{invert, CubeMove};
+use serde::Serialize;
+use std::fmt;
+
+// Parser for move sequences in standard (Singmaster) notation, for example
+//   R U R' U' (R U2)2 [R, U] [F: R U R'] // comments run to the end of the line
+// Supported:
+//   face turns U D L R F B, slices M E S, rotations x y z, wide turns Rw or r
+//   SiGN layer prefixes on face and wide turns, 2R and 3Rw
+//   amounts and primes: R2, R', R2', R3
+//   groups with repetition or inversion: (R U)2, (R U)'
+//   commutators [A, B] = A B A' B' and conjugates [A: B] = A B A', both may be nested and repeated
+
+// limits that keep a short input like (R)999999999 from expanding to more moves than fit in memory
+const MAX_REPETITIONS: usize = 1000;
+const MAX_MOVES: usize = 10_000;
+
+// Where and why a sequence failed to parse, line and column are 1-based and count characters
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+// parse a move sequence into the moves it expands to
+pub fn parse(input: &str) -> Result<Vec<CubeMove>, ParseError> {
+    parse_with(input, None)
+}
+
+// the same for a cube of the given size, layer prefixes beyond its layers like 4R on a 3x3 are errors
+pub fn parse_for_size(input: &str, size: usize) -> Result<Vec<CubeMove>, ParseError> {
+    parse_with(input, Some(size))
+}
+
+fn parse_with(input: &str, size: Option<usize>) -> Result<Vec<CubeMove>, ParseError> {
+    let mut parser = Parser {
+        chars: input.chars().collect(),
+        index: 0,
+        size,
+    };
+    let moves = parser.parse_sequence(&[])?;
+    match parser.peek() {
+        None => Ok(moves),
+        Some(c) => Err(parser.error(parser.index, format!("unexpected '{}'", c))),
+    }
+}
+
+// write moves back out in standard notation, separated by spaces
+pub fn format(moves: &[CubeMove]) -> String {
+    moves
+        .iter()
+        .map(|cube_move| cube_move.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn is_prime(c: char) -> bool {
+    matches!(c, '\'' | '’' | '′')
+}
+
+struct Parser {
+    chars: Vec<char>,
+    index: usize,
+    size: Option<usize>, // of the cube the moves are for, when known
+}
+
+impl Parser {
+    fn error(&self, index: usize, message: String) -> ParseError {
+        let before = &self.chars[..index.min(self.chars.len())];
+        let line = before.iter().filter(|c| **c == '\n').count() + 1;
+        let column = before.iter().rev().take_while(|c| **c != '\n').count() + 1;
+        ParseError {
+            line,
+            column,
+            message,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.index += 1;
+            } else if c == '/' && self.chars.get(self.index + 1) == Some(&'/') {
+                while !matches!(self.peek(), None | Some('\n')) {
+                    self.index += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    // read a run of digits right at the current position
+    fn parse_number(&mut self) -> Result<Option<usize>, ParseError> {
+        let start = self.index;
+        while matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+            self.index += 1;
+        }
+        if start == self.index {
+            return Ok(None);
+        }
+        let digits: String = self.chars[start..self.index].iter().collect();
+        digits
+            .parse()
+            .map(Some)
+            .map_err(|_| self.error(start, format!("number {} is too large", digits)))
+    }
+
+    // read everything up to one of the terminators (or the end of the input) without consuming it
+    fn parse_sequence(&mut self, terminators: &[char]) -> Result<Vec<CubeMove>, ParseError> {
+        let mut moves = Vec::new();
+        loop {
+            self.skip_whitespace_and_comments();
+            match self.peek() {
+                None => break,
+                Some(c) if terminators.contains(&c) => break,
+                Some(_) => {
+                    let start = self.index;
+                    moves.extend(self.parse_item()?);
+                    self.check_length(moves.len(), start)?;
+                }
+            }
+        }
+        Ok(moves)
+    }
+
+    // the error for a group starting at start that expands to length moves, if that is too many
+    fn check_length(&self, length: usize, start: usize) -> Result<(), ParseError> {
+        if length > MAX_MOVES {
+            return Err(self.error(
+                start,
+                format!("the sequence expands to more than {} moves", MAX_MOVES),
+            ));
+        }
+        Ok(())
+    }
+
+    fn parse_item(&mut self) -> Result<Vec<CubeMove>, ParseError> {
+        let start = self.index;
+        let moves = match self.peek() {
+            Some('(') => {
+                self.index += 1;
+                let moves = self.parse_sequence(&[')', ']', ',', ':'])?;
+                self.expect(')', start, "'(' is never closed")?;
+                moves
+            }
+            Some('[') => {
+                self.index += 1;
+                let first = self.parse_sequence(&[')', ']', ',', ':'])?;
+                let separator = self.index;
+                match self.peek() {
+                    Some(',') => {
+                        self.index += 1;
+                        let second = self.parse_sequence(&[')', ']', ',', ':'])?;
+                        self.expect(']', start, "'[' is never closed")?;
+                        // commutator A B A' B'
+                        [
+                            first.clone(),
+                            second.clone(),
+                            invert(&first),
+                            invert(&second),
+                        ]
+                        .concat()
+                    }
+                    Some(':') => {
+                        self.index += 1;
+                        let second = self.parse_sequence(&[')', ']', ',', ':'])?;
+                        self.expect(']', start, "'[' is never closed")?;
+                        // conjugate A B A'
+                        [first.clone(), second, invert(&first)].concat()
+                    }
+                    Some(']') => {
+                        return Err(self.error(separator, "expected ',' or ':' in brackets".into()))
+                    }
+                    Some(c) => return Err(self.error(separator, format!("unexpected '{}'", c))),
+                    None => return Err(self.error(start, "'[' is never closed".into())),
+                }
+            }
+            Some(c) if c.is_ascii_alphanumeric() => return self.parse_move(),
+            Some(c) => return Err(self.error(start, format!("unexpected '{}'", c))),
+            None => return Err(self.error(start, "unexpected end of input".into())),
+        };
+
+        self.check_length(moves.len(), start)?;
+
+        // groups can be repeated and inverted, (R U)2 or [R, U]'
+        let count_start = self.index;
+        let count = self.parse_number()?.unwrap_or(1);
+        if count > MAX_REPETITIONS {
+            return Err(self.error(
+                count_start,
+                format!("a group can be repeated at most {} times", MAX_REPETITIONS),
+            ));
+        }
+        self.check_length(moves.len() * count, start)?;
+        let moves = match self.peek() {
+            Some(c) if is_prime(c) => {
+                self.index += 1;
+                invert(&moves)
+            }
+            _ => moves,
+        };
+        Ok((0..count).flat_map(|_| moves.iter().cloned()).collect())
+    }
+
+    fn expect(&mut self, expected: char, open: usize, message: &str) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.index += 1;
+                Ok(())
+            }
+            Some(c) => Err(self.error(
+                self.index,
+                format!("expected '{}' but found '{}'", expected, c),
+            )),
+            None => Err(self.error(open, message.into())),
+        }
+    }
+
+    fn parse_move(&mut self) -> Result<Vec<CubeMove>, ParseError> {
+        let start = self.index;
+        let layer = self.parse_number()?;
+
+        // layer prefixes only go on outer face turns (2R) or Rw style wide turns (3Rw)
+        let name_start = self.index;
+        let (name, takes_layer_prefix) = match self.peek() {
+            Some(c @ ('U' | 'D' | 'L' | 'R' | 'F' | 'B')) => {
+                self.index += 1;
+                if self.peek() == Some('w') {
+                    self.index += 1;
+                    (format!("{}w", c), true)
+                } else {
+                    (c.to_string(), true)
+                }
+            }
+            // lowercase face letters are the older spelling of two layer wide turns
+            Some(c @ ('u' | 'd' | 'l' | 'r' | 'f' | 'b')) => {
+                self.index += 1;
+                (format!("{}w", c.to_ascii_uppercase()), false)
+            }
+            Some(c @ ('M' | 'E' | 'S' | 'x' | 'y' | 'z')) => {
+                self.index += 1;
+                (c.to_string(), false)
+            }
+            Some(c) => return Err(self.error(name_start, format!("unknown move '{}'", c))),
+            None => return Err(self.error(name_start, "expected a move".into())),
+        };
+        let family = CubeMove::family_from_name(&name).unwrap();
+
+        // R2 turns twice, R' backwards and R2' the same as R2
+        let amount = self.parse_number()?.unwrap_or(1) % 4;
+        let turns = match self.peek() {
+            Some(c) if is_prime(c) => {
+                self.index += 1;
+                (4 - amount) % 4
+            }
+            _ => amount,
+        };
+
+        let cube_move = match CubeMove::from_family_and_turns(family, turns) {
+            Some(cube_move) => cube_move,
+            None => return Ok(vec![]),
+        };
+        match layer {
+            None => Ok(vec![cube_move]),
+            Some(0) => Err(self.error(start, "layers are counted from 1".into())),
+            Some(layer) if self.size.is_some_and(|size| layer > size) => Err(self.error(
+                start,
+                format!("a {0}x{0} cube has no layer {1}", self.size.unwrap(), layer),
+            )),
+            Some(layer) if takes_layer_prefix => {
+                Ok(vec![CubeMove::Layered(layer, Box::new(cube_move))])
+            }
+            Some(_) => {
+                Err(self.error(start, format!("a layer prefix can't be used on '{}'", name)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_primes_amounts_and_groups() {
+        assert_eq!(
+            parse("R U' (R U2)2").unwrap(),
+            vec![
+                CubeMove::R,
+                CubeMove::UPrime,
+                CubeMove::R,
+                CubeMove::U2,
+                CubeMove::R,
+                CubeMove::U2
+            ]
+        );
+        assert_eq!(
+            parse("R2' R3").unwrap(),
+            vec![CubeMove::R2, CubeMove::RPrime]
+        );
+        assert_eq!(parse("(R U)'").unwrap(), parse("U' R'").unwrap());
+    }
+
+    #[test]
+    fn expands_commutators_and_conjugates() {
+        assert_eq!(parse("[R, U]").unwrap(), parse("R U R' U'").unwrap());
+        assert_eq!(parse("[F: R U R']").unwrap(), parse("F R U R' F'").unwrap());
+        assert_eq!(
+            parse("[F: [R, U]]").unwrap(),
+            parse("F R U R' U' F'").unwrap()
+        );
+    }
+
+    #[test]
+    fn reads_layer_prefixes_and_comments() {
+        assert_eq!(
+            parse("3Rw 2R r // the rest is ignored\nx").unwrap(),
+            vec![
+                CubeMove::Layered(3, Box::new(CubeMove::Rw)),
+                CubeMove::Layered(2, Box::new(CubeMove::R)),
+                CubeMove::Rw,
+                CubeMove::X
+            ]
+        );
+        assert!(parse_for_size("4R", 3).is_err());
+        assert!(parse_for_size("4R", 4).is_ok());
+    }
+
+    #[test]
+    fn reports_the_position_of_errors() {
+        let error = parse("R U\nR Q").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 3));
+        let error = parse("(R U").unwrap_err();
+        assert_eq!((error.line, error.column), (1, 1));
+        let error = parse("[R U]").unwrap_err();
+        assert_eq!((error.line, error.column), (1, 5));
+    }
+
+    #[test]
+    fn limits_how_far_a_sequence_expands() {
+        let error = parse("(R)999999999999").unwrap_err();
+        assert_eq!(error.column, 4);
+        assert!(parse("((((R)99)99)99)99").is_err());
+        assert!(parse(
+            "[[[[[[[[[[[[[[[[R, U], U], U], U], U], U], U], U], U], U], U], U], U], U], U], U]"
+        )
+        .is_err());
+        assert_eq!(parse("(R U)500").unwrap().len(), 1000);
+    }
+}
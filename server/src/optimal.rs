@@ -0,0 +1,528 @@
+use crate::cube::{Cube, CubeMove, Metric};
+use crate::cubie::{CubieCube, FACE_TURNS};
+use crate::two_phase::{can_follow, SolveError, StopCheck};
+use crate::validation;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+// Optimal 3x3 solver in the style of Korf: IDA* guided by pattern databases
+// A pattern database holds, for every state of a group of pieces, how many moves it takes to solve just those
+// pieces. The most any of them says is a lower bound for the whole cube, so the search never misses a shorter
+// solution. There is one for the 8 corners and one each for two groups of 6 edges, built with a breadth first
+// search that takes a few minutes, so they are saved to disk and loaded from there afterwards.
+// Solving takes from seconds to hours depending on how deep the solution is, which is why the search can be
+// cancelled and reports each depth it starts.
+
+const N_CORNER_STATES: usize = 40320 * 2187; // 8! places times 3^7 twists
+const N_EDGE_STATES: usize = 665280 * 64; // 12 * 11 * 10 * 9 * 8 * 7 places times 2^6 flips
+const EDGE_GROUPS: [[usize; 6]; 2] = [[0, 1, 2, 3, 4, 5], [6, 7, 8, 9, 10, 11]];
+const UNKNOWN: u8 = 0xF;
+
+// pieces tracked by where they are, unlike the cubie cube which tracks what is in each slot
+#[derive(Clone)]
+pub(crate) struct PieceState {
+    pub(crate) corner_slots: [u8; 8],
+    pub(crate) corner_twists: [u8; 8],
+    pub(crate) edge_slots: [u8; 12],
+    pub(crate) edge_flips: [u8; 12],
+}
+
+// where a move takes the piece in each slot and how much it twists or flips it
+pub(crate) struct MoveEffect {
+    corner_slot: [u8; 8],
+    corner_twist: [u8; 8],
+    edge_slot: [u8; 12],
+    edge_flip: [u8; 12],
+}
+
+impl MoveEffect {
+    pub(crate) fn all() -> Vec<MoveEffect> {
+        FACE_TURNS
+            .iter()
+            .map(|face_turn| {
+                // the piece in slot cp[i] moves to slot i, the same way CubieCube::compose reads it
+                let cubie = CubieCube::from_move(face_turn).unwrap();
+                let mut effect = MoveEffect {
+                    corner_slot: [0; 8],
+                    corner_twist: [0; 8],
+                    edge_slot: [0; 12],
+                    edge_flip: [0; 12],
+                };
+                for i in 0..8 {
+                    effect.corner_slot[cubie.cp[i] as usize] = i as u8;
+                    effect.corner_twist[cubie.cp[i] as usize] = cubie.co[i];
+                }
+                for i in 0..12 {
+                    effect.edge_slot[cubie.ep[i] as usize] = i as u8;
+                    effect.edge_flip[cubie.ep[i] as usize] = cubie.eo[i];
+                }
+                effect
+            })
+            .collect()
+    }
+}
+
+impl PieceState {
+    pub(crate) fn from_cubie(cubie: &CubieCube) -> Self {
+        let mut state = PieceState {
+            corner_slots: [0; 8],
+            corner_twists: [0; 8],
+            edge_slots: [0; 12],
+            edge_flips: [0; 12],
+        };
+        for slot in 0..8 {
+            state.corner_slots[cubie.cp[slot] as usize] = slot as u8;
+            state.corner_twists[cubie.cp[slot] as usize] = cubie.co[slot];
+        }
+        for slot in 0..12 {
+            state.edge_slots[cubie.ep[slot] as usize] = slot as u8;
+            state.edge_flips[cubie.ep[slot] as usize] = cubie.eo[slot];
+        }
+        state
+    }
+
+    pub(crate) fn apply(&mut self, effect: &MoveEffect) {
+        for piece in 0..8 {
+            let slot = self.corner_slots[piece] as usize;
+            self.corner_slots[piece] = effect.corner_slot[slot];
+            self.corner_twists[piece] = (self.corner_twists[piece] + effect.corner_twist[slot]) % 3;
+        }
+        for piece in 0..12 {
+            let slot = self.edge_slots[piece] as usize;
+            self.edge_slots[piece] = effect.edge_slot[slot];
+            self.edge_flips[piece] = (self.edge_flips[piece] + effect.edge_flip[slot]) % 2;
+        }
+    }
+
+    fn corner_index(&self) -> usize {
+        let twist = self.corner_twists[..7]
+            .iter()
+            .fold(0, |twist, &t| twist * 3 + t as usize);
+        partial_permutation_rank(&self.corner_slots, 8) * 2187 + twist
+    }
+
+    fn edge_index(&self, group: &[usize; 6]) -> usize {
+        let slots = group.map(|piece| self.edge_slots[piece]);
+        let flips = group.iter().fold(0, |flips, &piece| {
+            flips * 2 + self.edge_flips[piece] as usize
+        });
+        partial_permutation_rank(&slots, 12) * 64 + flips
+    }
+
+    // a state with the corners at the index, edges are left solved
+    fn from_corner_index(index: usize) -> Self {
+        let mut state = PieceState::from_cubie(&CubieCube::identity());
+        let slots = partial_permutation_unrank(index / 2187, 8, 8);
+        state.corner_slots.copy_from_slice(&slots);
+        let mut twist = index % 2187;
+        for piece in (0..7).rev() {
+            state.corner_twists[piece] = (twist % 3) as u8;
+            twist /= 3;
+        }
+        state.corner_twists[7] = (3 - state.corner_twists[..7].iter().sum::<u8>() % 3) % 3;
+        state
+    }
+
+    // a state with the edges of the group at the index, the other pieces don't matter for the group
+    fn from_edge_index(index: usize, group: &[usize; 6]) -> Self {
+        let mut state = PieceState::from_cubie(&CubieCube::identity());
+        let slots = partial_permutation_unrank(index / 64, 12, 6);
+        let mut flips = index % 64;
+        for (i, &piece) in group.iter().enumerate().rev() {
+            state.edge_slots[piece] = slots[i];
+            state.edge_flips[piece] = (flips % 2) as u8;
+            flips /= 2;
+        }
+        state
+    }
+}
+
+// index of k distinct slots out of n, in order, the slots 0..k in order are 0
+pub(crate) fn partial_permutation_rank(slots: &[u8], n: usize) -> usize {
+    let mut used = 0u16;
+    slots.iter().enumerate().fold(0, |rank, (i, &slot)| {
+        let smaller_free = (!used & ((1 << slot) - 1)).count_ones() as usize;
+        used |= 1 << slot;
+        rank * (n - i) + smaller_free
+    })
+}
+
+pub(crate) fn partial_permutation_unrank(rank: usize, n: usize, k: usize) -> Vec<u8> {
+    let mut digits = vec![0; k];
+    let mut rest = rank;
+    for i in (0..k).rev() {
+        digits[i] = rest % (n - i);
+        rest /= n - i;
+    }
+    let mut free: Vec<u8> = (0..n as u8).collect();
+    digits.into_iter().map(|digit| free.remove(digit)).collect()
+}
+
+// distances packed two to a byte, they never go above 14
+pub(crate) struct PatternDatabase {
+    distances: Vec<u8>,
+}
+
+impl PatternDatabase {
+    pub(crate) fn get(&self, index: usize) -> u8 {
+        (self.distances[index / 2] >> ((index % 2) * 4)) & 0xF
+    }
+
+    fn set(&mut self, index: usize, distance: u8) {
+        let shift = (index % 2) * 4;
+        let byte = &mut self.distances[index / 2];
+        *byte = (*byte & !(0xF << shift)) | (distance << shift);
+    }
+
+    // breadth first search from solved, one depth at a time by scanning for the states found at the last depth
+    // progress is told how many states are known as each depth starts, None when stopped before it is done
+    pub(crate) fn generate(
+        size: usize,
+        solved: usize,
+        decode: impl Fn(usize) -> PieceState,
+        encode: impl Fn(&PieceState) -> usize,
+        stop: &mut StopCheck,
+        mut progress: impl FnMut(u8, usize),
+    ) -> Option<Self> {
+        let effects = MoveEffect::all();
+        let mut database = PatternDatabase {
+            distances: vec![UNKNOWN << 4 | UNKNOWN; size.div_ceil(2)],
+        };
+        database.set(solved, 0);
+        let mut known = 1;
+        for depth in 0.. {
+            progress(depth, known);
+            let mut found = 0;
+            for index in 0..size {
+                if stop.should_stop() {
+                    return None;
+                }
+                if database.get(index) != depth {
+                    continue;
+                }
+                let state = decode(index);
+                for effect in effects.iter() {
+                    let mut next = state.clone();
+                    next.apply(effect);
+                    let next_index = encode(&next);
+                    if database.get(next_index) == UNKNOWN {
+                        database.set(next_index, depth + 1);
+                        found += 1;
+                    }
+                }
+            }
+            if found == 0 {
+                break;
+            }
+            known += found;
+        }
+        Some(database)
+    }
+
+    // read the database from the file, or generate it and save it there when the file is missing or the wrong size
+    fn load_or_generate(
+        path: &Path,
+        size: usize,
+        generate: impl FnOnce() -> Option<Self>,
+    ) -> Option<Self> {
+        if let Ok(distances) = fs::read(path) {
+            if distances.len() == size.div_ceil(2) {
+                return Some(PatternDatabase { distances });
+            }
+        }
+        let database = generate()?;
+        // a failed save only means generating again next time
+        let temporary = path.with_extension("tmp");
+        let _ =
+            fs::write(&temporary, &database.distances).and_then(|_| fs::rename(&temporary, path));
+        Some(database)
+    }
+}
+
+// How far generating a missing pattern database has come, reported as each depth starts
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GenerationProgress {
+    pub database: String, // the file it is saved to
+    pub depth: usize,     // the states this many moves from solved are being found
+    pub states: usize,    // states whose distance is known so far
+}
+
+// The pattern databases of the corners and of both edge groups
+pub struct PatternDatabases {
+    corners: PatternDatabase,
+    edges: [PatternDatabase; 2],
+}
+
+impl PatternDatabases {
+    // load the databases from the directory, generating and saving the ones that aren't there yet
+    // set cancel from another thread to stop generating, which returns None, progress is told about every depth generated
+    pub fn load_or_generate(
+        directory: &Path,
+        cancel: &AtomicBool,
+        mut progress: impl FnMut(&GenerationProgress),
+    ) -> Option<Self> {
+        let _ = fs::create_dir_all(directory);
+        let solved = PieceState::from_cubie(&CubieCube::identity());
+        let mut stop = StopCheck::new(None, Some(cancel));
+        let mut report = |database: &str, depth: u8, states: usize| {
+            progress(&GenerationProgress {
+                database: database.to_string(),
+                depth: depth as usize,
+                states,
+            })
+        };
+        let corners = PatternDatabase::load_or_generate(
+            &directory.join("corners.pdb"),
+            N_CORNER_STATES,
+            || {
+                PatternDatabase::generate(
+                    N_CORNER_STATES,
+                    solved.corner_index(),
+                    PieceState::from_corner_index,
+                    PieceState::corner_index,
+                    &mut stop,
+                    |depth, states| report("corners.pdb", depth, states),
+                )
+            },
+        )?;
+        let mut edges = Vec::new();
+        for (group, pieces) in EDGE_GROUPS.iter().enumerate() {
+            let file = format!("edges_{}.pdb", group);
+            edges.push(PatternDatabase::load_or_generate(
+                &directory.join(&file),
+                N_EDGE_STATES,
+                || {
+                    PatternDatabase::generate(
+                        N_EDGE_STATES,
+                        solved.edge_index(pieces),
+                        |index| PieceState::from_edge_index(index, pieces),
+                        |state| state.edge_index(pieces),
+                        &mut stop,
+                        |depth, states| report(&file, depth, states),
+                    )
+                },
+            )?);
+        }
+        let edges = edges.try_into().ok()?;
+        Some(PatternDatabases { corners, edges })
+    }
+
+    // fewest moves the cube could possibly be solved in
+    fn lower_bound(&self, state: &PieceState) -> u8 {
+        self.corners
+            .get(state.corner_index())
+            .max(self.edges[0].get(state.edge_index(&EDGE_GROUPS[0])))
+            .max(self.edges[1].get(state.edge_index(&EDGE_GROUPS[1])))
+    }
+}
+
+// How far an optimal search has come, reported as each depth starts
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchProgress {
+    pub depth: usize, // every solution shorter than this has been ruled out
+    pub nodes: u64,   // states looked at so far
+}
+
+struct Search<'a> {
+    databases: &'a PatternDatabases,
+    effects: Vec<MoveEffect>,
+    costs: [usize; 18], // what each move counts as in the metric being optimized
+    stop: StopCheck<'a>,
+    moves: Vec<usize>,
+}
+
+impl Search<'_> {
+    fn search(&mut self, state: &PieceState, togo: usize) -> bool {
+        if self.stop.should_stop() {
+            return false;
+        }
+        // the databases count face turns, which is never more than any metric counts
+        let lower_bound = self.databases.lower_bound(state) as usize;
+        if lower_bound > togo {
+            return false;
+        }
+        if togo == 0 {
+            return lower_bound == 0;
+        }
+        for m in 0..FACE_TURNS.len() {
+            if self.costs[m] > togo || !can_follow(self.moves.last(), m) {
+                continue;
+            }
+            let mut next = state.clone();
+            next.apply(&self.effects[m]);
+            self.moves.push(m);
+            if self.search(&next, togo - self.costs[m]) {
+                return true;
+            }
+            self.moves.pop();
+        }
+        false
+    }
+}
+
+// the shortest possible solution of a 3x3 in the metric, made of outer face turns
+// set cancel from another thread to stop the search early, progress is told about every depth before it is searched
+pub fn solve(
+    cube: &Cube,
+    databases: &PatternDatabases,
+    metric: Metric,
+    cancel: &AtomicBool,
+    mut progress: impl FnMut(&SearchProgress),
+) -> Result<Vec<CubeMove>, SolveError> {
+    validation::validate(cube).map_err(SolveError::Unsolvable)?;
+    let cubie = CubieCube::relative_to_centers(cube).map_err(SolveError::UnsupportedCube)?;
+
+    let state = PieceState::from_cubie(&cubie);
+    let mut search = Search {
+        databases,
+        effects: MoveEffect::all(),
+        costs: FACE_TURNS.each_ref().map(|m| m.count(metric, 3)),
+        stop: StopCheck::new(None, Some(cancel)),
+        moves: Vec::new(),
+    };
+    // every state is solvable in 20 face turns, or 26 quarter turns
+    for depth in databases.lower_bound(&state) as usize..=26 {
+        progress(&SearchProgress {
+            depth,
+            nodes: search.stop.nodes,
+        });
+        if search.search(&state, depth) {
+            return Ok(search
+                .moves
+                .iter()
+                .map(|&m| FACE_TURNS[m].clone())
+                .collect());
+        }
+        if search.stop.stopped {
+            return Err(SolveError::Cancelled);
+        }
+    }
+    Err(SolveError::NotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cube;
+    use crate::scramble::{random_state, rng_from_seed};
+
+    // generating the real databases takes minutes, these only know whether a group is solved,
+    // which still never says more than the moves left and is 0 only when the cube is solved
+    fn solved_or_not() -> PatternDatabases {
+        let database = |size: usize, solved: usize| {
+            let mut database = PatternDatabase {
+                distances: vec![0x11; size.div_ceil(2)],
+            };
+            database.set(solved, 0);
+            database
+        };
+        let solved = PieceState::from_cubie(&CubieCube::identity());
+        PatternDatabases {
+            corners: database(N_CORNER_STATES, solved.corner_index()),
+            edges: EDGE_GROUPS.map(|group| database(N_EDGE_STATES, solved.edge_index(&group))),
+        }
+    }
+
+    fn scrambled(moves: &[CubeMove]) -> Cube {
+        let mut cube = Cube::new();
+        for cube_move in moves {
+            cube.apply_move(cube_move);
+        }
+        cube
+    }
+
+    #[test]
+    fn finds_the_shortest_solution() {
+        let databases = solved_or_not();
+        let scramble = [CubeMove::R, CubeMove::U2, CubeMove::FPrime, CubeMove::L];
+        let mut cube = scrambled(&scramble);
+        let mut depths = Vec::new();
+        let moves = solve(
+            &cube,
+            &databases,
+            Metric::Htm,
+            &AtomicBool::new(false),
+            |progress| depths.push(progress.depth),
+        )
+        .unwrap();
+        assert_eq!(moves, cube::invert(&scramble));
+        assert_eq!(depths, [1, 2, 3, 4]);
+        for cube_move in moves.iter() {
+            cube.apply_move(cube_move);
+        }
+        assert!(cube.is_solved());
+    }
+
+    #[test]
+    fn counts_moves_in_the_metric() {
+        let databases = solved_or_not();
+        let cube = scrambled(&[CubeMove::R2, CubeMove::U]);
+        let moves = solve(
+            &cube,
+            &databases,
+            Metric::Qtm,
+            &AtomicBool::new(false),
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(cube::length(&moves, Metric::Qtm, 3), 3);
+    }
+
+    #[test]
+    fn stops_when_cancelled() {
+        let databases = solved_or_not();
+        let cube = Cube::try_from(&random_state(&mut rng_from_seed(1))).unwrap();
+        assert_eq!(
+            solve(
+                &cube,
+                &databases,
+                Metric::Htm,
+                &AtomicBool::new(true),
+                |_| {}
+            ),
+            Err(SolveError::Cancelled)
+        );
+    }
+
+    #[test]
+    fn stops_generating_when_cancelled() {
+        let directory = std::env::temp_dir().join("rubiks_cube_cancelled_pattern_databases");
+        let mut reported = Vec::new();
+        let databases =
+            PatternDatabases::load_or_generate(&directory, &AtomicBool::new(true), |progress| {
+                reported.push(progress.clone())
+            });
+        assert!(databases.is_none());
+        assert_eq!(
+            reported,
+            vec![GenerationProgress {
+                database: "corners.pdb".to_string(),
+                depth: 0,
+                states: 1,
+            }]
+        );
+        assert!(!directory.join("corners.pdb").exists());
+    }
+
+    #[test]
+    fn indexes_states_the_way_they_are_decoded() {
+        let mut rng = rng_from_seed(2);
+        for _ in 0..10 {
+            let state = PieceState::from_cubie(&random_state(&mut rng));
+            let corners = state.corner_index();
+            assert_eq!(
+                PieceState::from_corner_index(corners).corner_index(),
+                corners
+            );
+            for group in EDGE_GROUPS.iter() {
+                let edges = state.edge_index(group);
+                assert_eq!(
+                    PieceState::from_edge_index(edges, group).edge_index(group),
+                    edges
+                );
+            }
+        }
+    }
+}
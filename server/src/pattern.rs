@@ -0,0 +1,380 @@
+use crate::cube::{parse_facelets, Cube, CubeMove, Direction, FaceletError, Metric};
+use crate::cubie::{Corner, CubieCube, CubieError, Edge, FACE_TURNS};
+use crate::optimal::{
+    partial_permutation_rank, partial_permutation_unrank, MoveEffect, PatternDatabase, PieceState,
+};
+use crate::two_phase::{can_follow, SolveError, StopCheck};
+use crate::validation;
+use std::time::{Duration, Instant};
+use strum::IntoEnumIterator;
+
+// Patterns of stickers where only some of them matter, like a solved cross or the first two layers
+// A pattern is written as a facelet string with an X for every sticker that can be anything, and is compared
+// with the facelets of a cube, so it sees the cube from its centers the same way the solvers do.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    size: usize,
+    stickers: Vec<Option<Direction>>, // the face each sticker has to belong to in facelet order, None when any will do
+}
+
+impl Pattern {
+    // read a masked facelet string, the letters of Cube::to_facelets with X for the stickers left out
+    pub fn from_facelets(facelets: &str) -> Result<Self, FaceletError> {
+        let (size, stickers) = parse_facelets(facelets, true)?;
+        Ok(Pattern { size, stickers })
+    }
+
+    // every sticker on its own face
+    pub fn solved(size: usize) -> Self {
+        Pattern::from_facelets(&Cube::with_size(size).to_facelets()).unwrap()
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn to_facelets(&self) -> String {
+        self.stickers
+            .iter()
+            .map(|side| side.as_ref().map_or('X', Direction::letter))
+            .collect()
+    }
+
+    // whether every sticker the pattern cares about is on the face it should be
+    pub fn matches(&self, cube: &Cube) -> bool {
+        cube.size() == self.size
+            && cube
+                .to_facelets()
+                .chars()
+                .zip(self.stickers.iter())
+                .all(|(letter, side)| side.as_ref().is_none_or(|side| side.letter() == letter))
+    }
+
+    // whether the cube matches after turning the U layer, or without turning it
+    pub fn matches_up_to_auf(&self, cube: &Cube) -> bool {
+        let mut turned = cube.clone();
+        for _ in 0..4 {
+            if self.matches(&turned) {
+                return true;
+            }
+            turned.apply_move(&CubeMove::U);
+        }
+        false
+    }
+}
+
+// pieces whose slots and twists are looked up together in a pattern database
+struct PieceGroup {
+    corners: bool,
+    pieces: Vec<usize>,
+}
+
+impl PieceGroup {
+    fn slot_count(&self) -> usize {
+        if self.corners {
+            8
+        } else {
+            12
+        }
+    }
+
+    fn twist_count(&self) -> usize {
+        if self.corners {
+            3
+        } else {
+            2
+        }
+    }
+
+    fn size(&self) -> usize {
+        let placements: usize =
+            (self.slot_count() - self.pieces.len() + 1..=self.slot_count()).product();
+        placements * self.twist_count().pow(self.pieces.len() as u32)
+    }
+
+    fn index(&self, state: &PieceState) -> usize {
+        let (slots, twists) = if self.corners {
+            (&state.corner_slots[..], &state.corner_twists[..])
+        } else {
+            (&state.edge_slots[..], &state.edge_flips[..])
+        };
+        let group_slots: Vec<u8> = self.pieces.iter().map(|&piece| slots[piece]).collect();
+        let twist = self.pieces.iter().fold(0, |twist, &piece| {
+            twist * self.twist_count() + twists[piece] as usize
+        });
+        partial_permutation_rank(&group_slots, self.slot_count())
+            * self.twist_count().pow(self.pieces.len() as u32)
+            + twist
+    }
+
+    // a state with the pieces of the group at the index, the other pieces don't matter for the group
+    fn state(&self, index: usize) -> PieceState {
+        let mut state = PieceState::from_cubie(&CubieCube::identity());
+        let twist_states = self.twist_count().pow(self.pieces.len() as u32);
+        let group_slots =
+            partial_permutation_unrank(index / twist_states, self.slot_count(), self.pieces.len());
+        let mut twist = index % twist_states;
+        for (i, &piece) in self.pieces.iter().enumerate().rev() {
+            let piece_twist = (twist % self.twist_count()) as u8;
+            twist /= self.twist_count();
+            if self.corners {
+                state.corner_slots[piece] = group_slots[i];
+                state.corner_twists[piece] = piece_twist;
+            } else {
+                state.edge_slots[piece] = group_slots[i];
+                state.edge_flips[piece] = piece_twist;
+            }
+        }
+        state
+    }
+}
+
+// the pieces a group has at most, which keeps its database to a few hundred thousand states
+const MAX_GROUP_PIECES: usize = 4;
+
+struct Search {
+    corners: [[Option<Direction>; 3]; 8], // the face each sticker of a corner slot has to belong to, in Corner::faces order
+    edges: [[Option<Direction>; 2]; 12],  // the same for the edge slots, in Edge::faces order
+    groups: Vec<(PieceGroup, PatternDatabase)>,
+    effects: Vec<MoveEffect>,
+    costs: [usize; 18],
+    stop: StopCheck<'static>,
+    moves: Vec<usize>,
+}
+
+impl Search {
+    fn new(pattern: &Pattern, metric: Metric, deadline: Option<Instant>) -> Self {
+        let mut corners: [[Option<Direction>; 3]; 8] = Default::default();
+        let mut edges: [[Option<Direction>; 2]; 12] = Default::default();
+        for ((position, side), wanted) in Cube::new()
+            .facelet_positions()
+            .into_iter()
+            .zip(pattern.stickers.iter())
+        {
+            if let Some(slot) = Corner::from_grid_position(position) {
+                let facet = slot.faces().iter().position(|face| *face == side).unwrap();
+                corners[slot as usize][facet] = wanted.clone();
+            } else if let Some(slot) = Edge::from_grid_position(position) {
+                let facet = slot.faces().iter().position(|face| *face == side).unwrap();
+                edges[slot as usize][facet] = wanted.clone();
+            }
+        }
+
+        // a piece is pinned when the pattern asks for all of its stickers at home, which only it can fill
+        let pinned_corners: Vec<usize> = Corner::iter()
+            .filter(|corner| corners[*corner as usize] == corner.faces().map(Some))
+            .map(|corner| corner as usize)
+            .collect();
+        let pinned_edges: Vec<usize> = Edge::iter()
+            .filter(|edge| edges[*edge as usize] == edge.faces().map(Some))
+            .map(|edge| edge as usize)
+            .collect();
+
+        let solved = PieceState::from_cubie(&CubieCube::identity());
+        let groups = pinned_corners
+            .chunks(MAX_GROUP_PIECES)
+            .map(|pieces| (true, pieces))
+            .chain(
+                pinned_edges
+                    .chunks(MAX_GROUP_PIECES)
+                    .map(|pieces| (false, pieces)),
+            )
+            .map(|(corners, pieces)| {
+                let group = PieceGroup {
+                    corners,
+                    pieces: pieces.to_vec(),
+                };
+                let database = PatternDatabase::generate(
+                    group.size(),
+                    group.index(&solved),
+                    |index| group.state(index),
+                    |state| group.index(state),
+                    &mut StopCheck::new(None, None),
+                    |_, _| {},
+                )
+                .unwrap();
+                (group, database)
+            })
+            .collect();
+
+        Search {
+            corners,
+            edges,
+            groups,
+            effects: MoveEffect::all(),
+            costs: FACE_TURNS.each_ref().map(|m| m.count(metric, 3)),
+            stop: StopCheck::new(deadline, None),
+            moves: Vec::new(),
+        }
+    }
+
+    // fewest moves that could bring the pinned pieces home
+    fn lower_bound(&self, state: &PieceState) -> usize {
+        self.groups
+            .iter()
+            .map(|(group, database)| database.get(group.index(state)) as usize)
+            .max()
+            .unwrap_or(0)
+    }
+
+    // the twist is which face of the slot the first sticker of the piece is on, the others follow around it
+    fn matches(&self, state: &PieceState) -> bool {
+        let fits = |wanted: &Option<Direction>, side: &Direction| {
+            wanted.as_ref().is_none_or(|wanted| wanted == side)
+        };
+        Corner::iter().all(|corner| {
+            let (slot, twist) = (
+                state.corner_slots[corner as usize] as usize,
+                state.corner_twists[corner as usize] as usize,
+            );
+            corner
+                .faces()
+                .iter()
+                .enumerate()
+                .all(|(facet, side)| fits(&self.corners[slot][(facet + twist) % 3], side))
+        }) && Edge::iter().all(|edge| {
+            let (slot, flip) = (
+                state.edge_slots[edge as usize] as usize,
+                state.edge_flips[edge as usize] as usize,
+            );
+            edge.faces()
+                .iter()
+                .enumerate()
+                .all(|(facet, side)| fits(&self.edges[slot][(facet + flip) % 2], side))
+        })
+    }
+
+    fn search(&mut self, state: &PieceState, togo: usize) -> bool {
+        if self.stop.should_stop() {
+            return false;
+        }
+        let lower_bound = self.lower_bound(state);
+        if lower_bound > togo {
+            return false;
+        }
+        // shorter sequences were all tried at lower depths, so only those that use up the budget are new
+        if togo < self.costs.iter().min().copied().unwrap_or(1) && self.matches(state) {
+            return true;
+        }
+        for m in 0..FACE_TURNS.len() {
+            if self.costs[m] > togo || !can_follow(self.moves.last(), m) {
+                continue;
+            }
+            let mut next = state.clone();
+            next.apply(&self.effects[m]);
+            self.moves.push(m);
+            if self.search(&next, togo - self.costs[m]) {
+                return true;
+            }
+            self.moves.pop();
+        }
+        false
+    }
+}
+
+// the fewest outer face turns, counted in the metric, that bring a 3x3 into the pattern, trying up to max_length
+// pieces the pattern pins to their home are looked up in pattern databases built for them, so patterns made of
+// solved pieces like a cross are found quickly and patterns of lone stickers take longer
+pub fn solve(
+    cube: &Cube,
+    pattern: &Pattern,
+    max_length: usize,
+    metric: Metric,
+    timeout: Option<Duration>,
+) -> Result<Vec<CubeMove>, SolveError> {
+    if pattern.size != 3 {
+        return Err(SolveError::UnsupportedCube(CubieError::UnsupportedSize(
+            pattern.size,
+        )));
+    }
+    validation::validate(cube).map_err(SolveError::Unsolvable)?;
+    let cubie = CubieCube::relative_to_centers(cube).map_err(SolveError::UnsupportedCube)?;
+    // face turns never move the centers, the fifth sticker of each face, so they have to be right already
+    let centers_fit = pattern
+        .stickers
+        .iter()
+        .zip(Pattern::solved(3).stickers)
+        .skip(4)
+        .step_by(9)
+        .all(|(wanted, center)| wanted.is_none() || *wanted == center);
+    if !centers_fit {
+        return Err(SolveError::PatternCentersUnreachable);
+    }
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut search = Search::new(pattern, metric, deadline);
+    let state = PieceState::from_cubie(&cubie);
+    for depth in search.lower_bound(&state)..=max_length {
+        if search.search(&state, depth) {
+            return Ok(search
+                .moves
+                .iter()
+                .map(|&m| FACE_TURNS[m].clone())
+                .collect());
+        }
+        if search.stop.stopped {
+            break;
+        }
+    }
+    Err(SolveError::NotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CROSS_ON_D: &str = "XXXXUXXXXXXXXRXXRXXXXXFXXFXXDXDDDXDXXXXXLXXLXXXXXBXXBX";
+
+    fn scrambled(moves: &[CubeMove]) -> Cube {
+        let mut cube = Cube::new();
+        for cube_move in moves {
+            cube.apply_move(cube_move);
+        }
+        cube
+    }
+
+    #[test]
+    fn solves_the_cross_in_the_fewest_moves() {
+        let pattern = Pattern::from_facelets(CROSS_ON_D).unwrap();
+        let mut cube = scrambled(&[CubeMove::F2, CubeMove::U, CubeMove::R, CubeMove::L2]);
+        assert!(!pattern.matches(&cube));
+        let moves = solve(&cube, &pattern, 12, Metric::Htm, None).unwrap();
+        assert!(moves.len() <= 4);
+        for cube_move in moves.iter() {
+            cube.apply_move(cube_move);
+        }
+        assert!(pattern.matches(&cube));
+    }
+
+    #[test]
+    fn gives_up_past_the_longest_allowed() {
+        let pattern = Pattern::from_facelets(CROSS_ON_D).unwrap();
+        let cube = scrambled(&[CubeMove::F2, CubeMove::U, CubeMove::R, CubeMove::L2]);
+        assert_eq!(
+            solve(&cube, &pattern, 1, Metric::Htm, None),
+            Err(SolveError::NotFound)
+        );
+    }
+
+    #[test]
+    fn rejects_patterns_with_moved_centers() {
+        let mut facelets = CROSS_ON_D.to_string();
+        facelets.replace_range(4..5, "F");
+        let pattern = Pattern::from_facelets(&facelets).unwrap();
+        let error = solve(&Cube::new(), &pattern, 12, Metric::Htm, None).unwrap_err();
+        assert_eq!(error, SolveError::PatternCentersUnreachable);
+        assert_eq!(
+            error.to_string(),
+            "face turns can't move the centers where the pattern has them"
+        );
+    }
+
+    #[test]
+    fn rejects_patterns_for_other_sizes() {
+        assert_eq!(
+            solve(&Cube::new(), &Pattern::solved(2), 12, Metric::Htm, None),
+            Err(SolveError::UnsupportedCube(CubieError::UnsupportedSize(2)))
+        );
+    }
+}
@@ -0,0 +1,397 @@
+use crate::cube::{simplify, Cube, CubeMove, Metric};
+use crate::cubie::{Corner, CubieCube, CubieError};
+use crate::two_phase::SolveError;
+use crate::validation;
+use nalgebra::Matrix3;
+use rand::Rng;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::OnceLock;
+use strum::IntoEnumIterator;
+
+// Pocket cube (2x2) model with a table of the distance to solved of every state
+// A 2x2 has no centers, so states are counted with the DBL corner held in place and only U, R and F turned.
+// That leaves 7! * 3^6 = 3,674,160 states, few enough to find the distance of each one with a breadth first
+// search. The tables make every solution optimal and instant, and random states exactly uniform.
+// There is one table counting face turns and one counting quarter turns, the other metrics count
+// U, R and F turns the same way as face turns.
+
+const N_PERM: usize = 5040; // 7! orders of the corners around DBL
+const N_TWIST: usize = 729; // 3^6 twists, the last corner follows from the others
+pub const N_STATES: usize = N_PERM * N_TWIST;
+const UNKNOWN: u8 = u8::MAX;
+
+// the moves that keep DBL in place, move m turns face m / 3 by m % 3 + 1 quarter turns
+pub const POCKET_MOVES: [CubeMove; 9] = [
+    CubeMove::U,
+    CubeMove::U2,
+    CubeMove::UPrime,
+    CubeMove::R,
+    CubeMove::R2,
+    CubeMove::RPrime,
+    CubeMove::F,
+    CubeMove::F2,
+    CubeMove::FPrime,
+];
+
+// the slots around DBL, in the order the permutation coordinate reads them
+const FREE_SLOTS: [usize; 7] = [0, 1, 2, 3, 4, 5, 7];
+
+// Corners of a 2x2 held with DBL in place, the same way CubieCube describes the corners of a 3x3
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PocketCube {
+    pub cp: [Corner; 8], // corner in each corner slot
+    pub co: [u8; 8],     // clockwise twist of the corner in each slot, 0..3
+}
+
+impl PocketCube {
+    // the solved cube
+    pub fn identity() -> Self {
+        let identity = CubieCube::identity();
+        PocketCube {
+            cp: identity.cp,
+            co: identity.co,
+        }
+    }
+
+    // the pocket cube of a single move, only U, R and F turns keep DBL in place
+    pub fn from_move(cube_move: &CubeMove) -> Option<Self> {
+        if !POCKET_MOVES.contains(cube_move) {
+            return None;
+        }
+        let cubie = CubieCube::from_move(cube_move)?;
+        Some(PocketCube {
+            cp: cubie.cp,
+            co: cubie.co,
+        })
+    }
+
+    // apply other after self, like CubieCube::compose
+    pub fn compose(&self, other: &PocketCube) -> PocketCube {
+        let mut result = PocketCube::identity();
+        for i in 0..8 {
+            let from = other.cp[i] as usize;
+            result.cp[i] = self.cp[from];
+            result.co[i] = (self.co[from] + other.co[i]) % 3;
+        }
+        result
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == PocketCube::identity()
+    }
+
+    // the fewest moves that solve it in the metric, None for the states a real cube can't be in
+    pub fn distance(&self, metric: Metric) -> Option<usize> {
+        match tables().distances(metric).0[self.index()] {
+            UNKNOWN => None,
+            distance => Some(distance as usize),
+        }
+    }
+
+    fn index(&self) -> usize {
+        perm(self) * N_TWIST + twist(self)
+    }
+
+    fn from_index(index: usize) -> Self {
+        let mut pocket = set_perm(index / N_TWIST);
+        pocket.co = set_twist(index % N_TWIST).co;
+        pocket
+    }
+}
+
+impl Default for PocketCube {
+    fn default() -> Self {
+        PocketCube::identity()
+    }
+}
+
+// which corner sits in each slot around DBL, as 0..7
+fn perm(pocket: &PocketCube) -> usize {
+    let permutation = FREE_SLOTS.map(|slot| {
+        let corner = pocket.cp[slot] as usize;
+        corner - (corner > Corner::DBL as usize) as usize
+    });
+    permutation.iter().enumerate().fold(0, |rank, (i, &piece)| {
+        let smaller_after = permutation[i + 1..]
+            .iter()
+            .filter(|&&other| other < piece)
+            .count();
+        rank * (7 - i) + smaller_after
+    })
+}
+
+fn set_perm(coordinate: usize) -> PocketCube {
+    let mut digits = [0; 7];
+    let mut rest = coordinate;
+    for i in (0..7).rev() {
+        digits[i] = rest % (7 - i);
+        rest /= 7 - i;
+    }
+    let mut remaining: Vec<Corner> = Corner::iter()
+        .filter(|&corner| corner != Corner::DBL)
+        .collect();
+    let mut pocket = PocketCube::identity();
+    for (i, slot) in FREE_SLOTS.iter().enumerate() {
+        pocket.cp[*slot] = remaining.remove(digits[i]);
+    }
+    pocket
+}
+
+fn twist(pocket: &PocketCube) -> usize {
+    pocket.co[..6]
+        .iter()
+        .fold(0, |twist, &t| twist * 3 + t as usize)
+}
+
+fn set_twist(coordinate: usize) -> PocketCube {
+    let mut pocket = PocketCube::identity();
+    let mut rest = coordinate;
+    for slot in (0..6).rev() {
+        pocket.co[slot] = (rest % 3) as u8;
+        rest /= 3;
+    }
+    pocket.co[7] = (3 - pocket.co[..6].iter().sum::<u8>() % 3) % 3;
+    pocket
+}
+
+// the moves counted as one in each table, half turns are two quarter turns
+const FACE_TURNS: [usize; 9] = [0, 1, 2, 3, 4, 5, 6, 7, 8];
+const QUARTER_TURNS: [usize; 6] = [0, 2, 3, 5, 6, 8];
+
+// move tables give the coordinate after each move, distances are indexed by perm * N_TWIST + twist
+struct Tables {
+    perm_move: Vec<[u16; 9]>,
+    twist_move: Vec<[u16; 9]>,
+    distances: Vec<u8>,
+    quarter_turn_distances: Vec<u8>,
+}
+
+impl Tables {
+    fn next(&self, index: usize, m: usize) -> usize {
+        let (p, t) = (index / N_TWIST, index % N_TWIST);
+        self.perm_move[p][m] as usize * N_TWIST + self.twist_move[t][m] as usize
+    }
+
+    // the distances in the metric and the moves that count as one in it
+    fn distances(&self, metric: Metric) -> (&[u8], &'static [usize]) {
+        match metric {
+            Metric::Qtm => (&self.quarter_turn_distances, &QUARTER_TURNS),
+            _ => (&self.distances, &FACE_TURNS),
+        }
+    }
+
+    // breadth first search from solved using only the given moves
+    fn distance_table(&self, moves: &[usize]) -> Vec<u8> {
+        let mut distances = vec![UNKNOWN; N_STATES];
+        let solved = PocketCube::identity().index();
+        distances[solved] = 0;
+        let mut queue = VecDeque::from([solved]);
+        while let Some(index) = queue.pop_front() {
+            for &m in moves {
+                let next = self.next(index, m);
+                if distances[next] == UNKNOWN {
+                    distances[next] = distances[index] + 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+        distances
+    }
+}
+
+static TABLES: OnceLock<Tables> = OnceLock::new();
+
+fn tables() -> &'static Tables {
+    TABLES.get_or_init(|| {
+        let moves: Vec<PocketCube> = POCKET_MOVES
+            .iter()
+            .map(|cube_move| PocketCube::from_move(cube_move).unwrap())
+            .collect();
+        let perm_move: Vec<[u16; 9]> = (0..N_PERM)
+            .map(|coordinate| {
+                let pocket = set_perm(coordinate);
+                std::array::from_fn(|m| perm(&pocket.compose(&moves[m])) as u16)
+            })
+            .collect();
+        let twist_move: Vec<[u16; 9]> = (0..N_TWIST)
+            .map(|coordinate| {
+                let pocket = set_twist(coordinate);
+                std::array::from_fn(|m| twist(&pocket.compose(&moves[m])) as u16)
+            })
+            .collect();
+
+        let mut tables = Tables {
+            perm_move,
+            twist_move,
+            distances: Vec::new(),
+            quarter_turn_distances: Vec::new(),
+        };
+        tables.distances = tables.distance_table(&FACE_TURNS);
+        tables.quarter_turn_distances = tables.distance_table(&QUARTER_TURNS);
+        tables
+    })
+}
+
+// how many states are each number of moves away from solved in the metric, the longest take 11 face turns
+pub fn distance_histogram(metric: Metric) -> Vec<usize> {
+    let mut histogram = Vec::new();
+    for &distance in tables().distances(metric).0.iter() {
+        if histogram.len() <= distance as usize {
+            histogram.resize(distance as usize + 1, 0);
+        }
+        histogram[distance as usize] += 1;
+    }
+    histogram
+}
+
+// a uniformly random state that a real 2x2 can be in
+pub fn random_state(rng: &mut impl Rng) -> PocketCube {
+    PocketCube::from_index(rng.gen_range(0..N_STATES))
+}
+
+// an optimal solution in U, R and F turns for the metric, found by always taking a move that gets one closer to solved
+pub fn solve_pocket(pocket: &PocketCube, metric: Metric) -> Option<Vec<CubeMove>> {
+    let tables = tables();
+    let (distances, unit_moves) = tables.distances(metric);
+    let mut index = pocket.index();
+    if distances[index] == UNKNOWN {
+        return None;
+    }
+    let mut moves = Vec::new();
+    while distances[index] > 0 {
+        let (m, next) = unit_moves
+            .iter()
+            .map(|&m| (m, tables.next(index, m)))
+            .find(|(_, next)| distances[*next] + 1 == distances[index])
+            .unwrap();
+        moves.push(POCKET_MOVES[m].clone());
+        index = next;
+    }
+    // counting quarter turns, a half turn is found as two quarter turns in a row
+    Some(simplify(&moves))
+}
+
+// the shortest solution of a 2x2 in the metric as it is held, turning the faces away from wherever the DBL corner is now
+pub fn solve(cube: &Cube, metric: Metric) -> Result<Vec<CubeMove>, SolveError> {
+    let pocket = PocketCube::try_from(cube).map_err(SolveError::UnsupportedCube)?;
+    validation::validate(cube).map_err(SolveError::Unsolvable)?;
+    let frame = dbl_orientation(cube);
+    let moves = solve_pocket(&pocket, metric).ok_or(SolveError::NotFound)?;
+    Ok(moves.iter().map(|m| m.transformed(&frame)).collect())
+}
+
+// how the whole cube is turned compared to holding the DBL corner at home
+fn dbl_orientation(cube: &Cube) -> Matrix3<i32> {
+    cube.pieces
+        .iter()
+        .find(|piece| {
+            Corner::from_grid_position(piece.get_home_grid_position() * 2) == Some(Corner::DBL)
+        })
+        .map(|piece| piece.get_orientation())
+        .unwrap()
+}
+
+impl TryFrom<&Cube> for PocketCube {
+    type Error = CubieError;
+
+    // read the corners as if the cube were turned to bring DBL home, so whole cube rotations don't matter
+    fn try_from(cube: &Cube) -> Result<Self, Self::Error> {
+        if cube.size() != 2 {
+            return Err(CubieError::UnsupportedSize(cube.size()));
+        }
+
+        let undo = dbl_orientation(cube).transpose();
+        let mut pocket = PocketCube::identity();
+        for piece in cube.pieces.iter() {
+            // a 2x2 corner sits one half unit out on each axis where a 3x3 corner sits two
+            let corner = Corner::from_grid_position(piece.get_home_grid_position() * 2).unwrap();
+            let slot = Corner::from_grid_position(undo * piece.get_grid_position() * 2).unwrap();
+            pocket.cp[slot as usize] = corner;
+            pocket.co[slot as usize] = corner.twist_in(slot, &(undo * piece.get_orientation()));
+        }
+        Ok(pocket)
+    }
+}
+
+impl From<&PocketCube> for Cube {
+    fn from(pocket: &PocketCube) -> Self {
+        let mut cube = Cube::with_size(2);
+        for piece in cube.pieces.iter_mut() {
+            let corner = Corner::from_grid_position(piece.get_home_grid_position() * 2).unwrap();
+            let slot = pocket.cp.iter().position(|c| *c == corner).unwrap();
+            let orientation =
+                corner.orientation_in(Corner::iter().nth(slot).unwrap(), pocket.co[slot]);
+            piece.set_orientation(orientation);
+        }
+        cube
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scramble::rng_from_seed;
+
+    #[test]
+    fn distances_match_the_known_counts() {
+        assert_eq!(
+            distance_histogram(Metric::Htm),
+            [1, 9, 54, 321, 1847, 9992, 50136, 227536, 870072, 1887748, 623800, 2644]
+        );
+        let quarter_turns = distance_histogram(Metric::Qtm);
+        assert_eq!(quarter_turns.len(), 15);
+        assert_eq!(quarter_turns.iter().sum::<usize>(), N_STATES);
+        assert_eq!(quarter_turns[14], 276);
+    }
+
+    #[test]
+    fn solves_random_states_optimally() {
+        let mut rng = rng_from_seed(1);
+        for metric in [Metric::Htm, Metric::Qtm] {
+            for _ in 0..10 {
+                let state = random_state(&mut rng);
+                let mut cube = Cube::from(&state);
+                let moves = solve(&cube, metric).unwrap();
+                assert_eq!(
+                    crate::cube::length(&moves, metric, 2),
+                    state.distance(metric).unwrap()
+                );
+                for cube_move in moves.iter() {
+                    cube.apply_move(cube_move);
+                }
+                assert!(cube.is_solved());
+            }
+        }
+    }
+
+    #[test]
+    fn solves_a_cube_held_another_way() {
+        let mut cube = Cube::with_size(2);
+        for cube_move in [
+            CubeMove::R,
+            CubeMove::Y,
+            CubeMove::D,
+            CubeMove::LPrime,
+            CubeMove::B2,
+        ] {
+            cube.apply_move(&cube_move);
+        }
+        let moves = solve(&cube, Metric::Htm).unwrap();
+        assert_eq!(moves.len(), 4);
+        for cube_move in moves.iter() {
+            cube.apply_move(cube_move);
+        }
+        assert!(cube.is_solved_up_to_rotation());
+    }
+
+    #[test]
+    fn rejects_other_sizes() {
+        let error = solve(&Cube::new(), Metric::Htm).unwrap_err();
+        assert_eq!(
+            error,
+            SolveError::UnsupportedCube(CubieError::UnsupportedSize(3))
+        );
+        assert_eq!(error.to_string(), "a 3x3 cube can't be solved this way");
+    }
+}
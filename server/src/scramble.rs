@@ -0,0 +1,242 @@
+use crate::cube::{invert, simplify, CubeMove, Metric};
+use crate::cubie::{is_odd_permutation, CubieCube};
+use crate::pocket;
+use crate::supercube;
+use crate::two_phase;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+// Scramble generation
+// A random state scramble picks one of the 43 quintillion reachable states of a 3x3 with equal chance and
+// then solves it, the scramble is the solution played backwards. This is how WCA scrambles are made.
+// A random move scramble just picks random moves, which is instant, works on every size and can be
+// limited to a set of moves for practicing, but doesn't mix the cube as evenly.
+
+// solutions found for scrambles are kept to this length, long enough that the search is quick
+const MAX_SCRAMBLE_LENGTH: usize = 21;
+
+// the longest random move scramble that can be asked for
+pub const MAX_RANDOM_MOVES: usize = 500;
+
+// the same seed always gives the same scramble
+pub fn rng_from_seed(seed: u64) -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(seed)
+}
+
+// a uniformly random state that a real cube can be in
+pub fn random_state(rng: &mut impl Rng) -> CubieCube {
+    let mut cubie = CubieCube::identity();
+    cubie.cp.shuffle(rng);
+    cubie.ep.shuffle(rng);
+    // half of all shuffles can't be reached by turning, swapping two edges moves those to the other half
+    let corner_permutation = cubie.cp.map(|corner| corner as usize);
+    let edge_permutation = cubie.ep.map(|edge| edge as usize);
+    if is_odd_permutation(&corner_permutation) != is_odd_permutation(&edge_permutation) {
+        cubie.ep.swap(0, 1);
+    }
+    // the last twist and flip follow from the others
+    for i in 0..7 {
+        cubie.co[i] = rng.gen_range(0..3);
+    }
+    cubie.co[7] = (3 - cubie.co[..7].iter().sum::<u8>() % 3) % 3;
+    for i in 0..11 {
+        cubie.eo[i] = rng.gen_range(0..2);
+    }
+    cubie.eo[11] = cubie.eo[..11].iter().sum::<u8>() % 2;
+    cubie
+}
+
+// moves that take a solved cube to a uniformly random state
+pub fn random_state_scramble(rng: &mut impl Rng) -> Vec<CubeMove> {
+    let state = random_state(rng);
+    let solution = two_phase::solve_cubie(&state, MAX_SCRAMBLE_LENGTH, Metric::Htm, None).unwrap();
+    invert(&solution)
+}
+
+// the same for a 3x3 supercube, whose centers end up turned a random way too
+pub fn random_supercube_state_scramble(rng: &mut impl Rng) -> Vec<CubeMove> {
+    let mut scramble = random_state_scramble(rng);
+    scramble.extend(supercube::random_center_turns(rng));
+    simplify(&scramble)
+}
+
+// random block turns for a cube bigger than 3x3, where finding a random state isn't practical
+// as long as WCA scrambles for these sizes, 40 moves on a 4x4 and 20 more for every layer after that
+pub fn random_big_cube_scramble(rng: &mut impl Rng, size: usize) -> Vec<CubeMove> {
+    random_move_scramble(rng, 20 * (size - 2), &MoveSet::block_turns(size))
+}
+
+// moves that take a solved 2x2 to a uniformly random state, the optimal solution of that state played backwards
+pub fn random_pocket_state_scramble(rng: &mut impl Rng) -> Vec<CubeMove> {
+    let state = pocket::random_state(rng);
+    invert(&pocket::solve_pocket(&state, Metric::Htm).unwrap())
+}
+
+// The moves a random move scramble picks from
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveSet {
+    moves: Vec<CubeMove>,
+}
+
+impl MoveSet {
+    // every outer face turn, the usual choice for a 3x3
+    pub fn face_turns() -> Self {
+        MoveSet::generated_by(&[
+            CubeMove::U,
+            CubeMove::D,
+            CubeMove::L,
+            CubeMove::R,
+            CubeMove::F,
+            CubeMove::B,
+        ])
+    }
+
+    // the face turns plus wide turns of up to half the layers, in every amount
+    pub fn block_turns(size: usize) -> Self {
+        let faces = [
+            CubeMove::U,
+            CubeMove::D,
+            CubeMove::L,
+            CubeMove::R,
+            CubeMove::F,
+            CubeMove::B,
+        ];
+        let wide = [
+            CubeMove::Uw,
+            CubeMove::Dw,
+            CubeMove::Lw,
+            CubeMove::Rw,
+            CubeMove::Fw,
+            CubeMove::Bw,
+        ];
+        let mut generators = faces.to_vec();
+        for layers in 2..=size / 2 {
+            generators.extend(wide.iter().map(|wide_turn| match layers {
+                2 => wide_turn.clone(),
+                layers => CubeMove::Layered(layers, Box::new(wide_turn.clone())),
+            }));
+        }
+        MoveSet::generated_by(&generators)
+    }
+
+    // every amount of each given move, so generated_by(&[R, U]) is <R, U>: R, R2, R', U, U2 and U'
+    pub fn generated_by(generators: &[CubeMove]) -> Self {
+        let mut moves: Vec<CubeMove> = Vec::new();
+        for generator in generators {
+            for times in 1..4 {
+                // repeating a move and merging the result gives its other amounts
+                if let [power] = &simplify(&vec![generator.clone(); times])[..] {
+                    if !moves.contains(power) {
+                        moves.push(power.clone());
+                    }
+                }
+            }
+        }
+        MoveSet { moves }
+    }
+
+    // exactly the given moves
+    pub fn from_moves(moves: Vec<CubeMove>) -> Self {
+        MoveSet { moves }
+    }
+
+    // drop the half turns, which are the moves that undo themselves
+    pub fn without_double_turns(self) -> Self {
+        MoveSet {
+            moves: self
+                .moves
+                .into_iter()
+                .filter(|cube_move| cube_move.inverse() != *cube_move)
+                .collect(),
+        }
+    }
+
+    pub fn moves(&self) -> &[CubeMove] {
+        &self.moves
+    }
+}
+
+// random moves from the move set, never turning a layer again before a move around another axis
+// so redundant runs like R R', R L R or U D2 U never show up
+// comes out shorter than length if the move set runs out of moves that can follow, as <R> does after one move
+pub fn random_move_scramble(
+    rng: &mut impl Rng,
+    length: usize,
+    move_set: &MoveSet,
+) -> Vec<CubeMove> {
+    let mut scramble: Vec<CubeMove> = Vec::new();
+    while scramble.len() < length {
+        let candidates: Vec<&CubeMove> = move_set
+            .moves
+            .iter()
+            .filter(|cube_move| {
+                scramble
+                    .iter()
+                    .rev()
+                    .take_while(|previous| previous.axis() == cube_move.axis())
+                    .all(|previous| previous.layer_group() != cube_move.layer_group())
+            })
+            .collect();
+        match candidates.choose(rng) {
+            Some(cube_move) => scramble.push((*cube_move).clone()),
+            None => break,
+        }
+    }
+    scramble
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_move_scrambles_have_no_redundant_moves() {
+        let mut rng = rng_from_seed(1);
+        let move_set = MoveSet::face_turns();
+        for _ in 0..20 {
+            let scramble = random_move_scramble(&mut rng, 25, &move_set);
+            assert_eq!(scramble.len(), 25);
+            for (index, cube_move) in scramble.iter().enumerate() {
+                // never the same layer again while only moves around the same axis came in between
+                let same_axis = scramble[..index]
+                    .iter()
+                    .rev()
+                    .take_while(|previous| previous.axis() == cube_move.axis());
+                assert!(
+                    same_axis
+                        .into_iter()
+                        .all(|previous| previous.layer_group() != cube_move.layer_group()),
+                    "{:?}",
+                    scramble
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn random_move_scrambles_stay_in_the_move_set() {
+        let mut rng = rng_from_seed(2);
+        let move_set = MoveSet::generated_by(&[CubeMove::R, CubeMove::U]).without_double_turns();
+        assert_eq!(move_set.moves().len(), 4);
+        let scramble = random_move_scramble(&mut rng, 30, &move_set);
+        assert_eq!(scramble.len(), 30);
+        assert!(scramble
+            .iter()
+            .all(|cube_move| move_set.moves().contains(cube_move)));
+
+        // <R> has nothing that can follow its first move
+        let only_r = MoveSet::generated_by(&[CubeMove::R]);
+        assert_eq!(random_move_scramble(&mut rng, 10, &only_r).len(), 1);
+    }
+
+    #[test]
+    fn big_cube_scrambles_are_as_long_as_wca_ones() {
+        let mut rng = rng_from_seed(3);
+        for (size, length) in [(4, 40), (5, 60), (7, 100)] {
+            let scramble = random_big_cube_scramble(&mut rng, size);
+            assert_eq!(scramble.len(), length);
+            assert!(scramble.iter().all(|cube_move| cube_move.fits(size)));
+        }
+    }
+}
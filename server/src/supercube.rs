@@ -0,0 +1,185 @@
+use crate::cube::{proper_rotations, simplify, Cube, CubeMove, Direction};
+use crate::cubie::{is_odd_permutation, CubieCube, CubieError};
+use crate::notation;
+use crate::two_phase::SolveError;
+use crate::validation::ValidationError;
+use nalgebra::Matrix3;
+use rand::Rng;
+use strum::IntoEnumIterator;
+
+// Centers of a 3x3 supercube
+// On a supercube the centers can be seen to turn, so a cube whose pieces are all solved can still have centers a
+// quarter or half turn off. The pieces can't tell, which lets any solver finish the pieces first and then the
+// centers are turned back with algorithms that leave every piece where it is.
+// A quarter turn of a face turns its center and swaps pieces an odd number of times, so the quarter turns the
+// centers are off add up to an even number exactly when the corners are an even permutation. Only half of the
+// ways to turn the centers go with a given state of the pieces.
+
+// turns the U center half a turn and leaves everything else as it was
+const HALF_TURN: &str = "U R L U2 R' L' U R L U2 R' L'";
+// turns the U center a quarter turn clockwise and the R center a quarter turn counterclockwise
+const ADJACENT_QUARTER_TURNS: &str = "F' R' L' U R L B' F D' U' R' D U B";
+// the same with the D center instead of the R center
+const OPPOSITE_QUARTER_TURNS: &str = "F R2 L2 F' B' U B' F' L2 R2 B F D' B";
+
+// the algorithm moved so that the U center turns as the first face and the second center as the second face
+fn moved(
+    algorithm: &str,
+    first: &Direction,
+    second: Option<(&Direction, &Direction)>,
+) -> Vec<CubeMove> {
+    let transform = proper_rotations()
+        .into_iter()
+        .find(|rotation| {
+            rotation * Direction::Up.grid_vector() == first.grid_vector()
+                && second.is_none_or(|(from, to)| rotation * from.grid_vector() == to.grid_vector())
+        })
+        .unwrap_or(Matrix3::identity());
+    notation::parse(algorithm)
+        .unwrap()
+        .iter()
+        .map(|cube_move| cube_move.transformed(&transform))
+        .collect()
+}
+
+// moves that turn each center by the quarter turns clockwise given for its face and leave the pieces alone
+// the quarter turns have to add up to an even number, the ones that are left over end up on the U center
+pub(crate) fn center_turns(quarter_turns: &[(Direction, u8)]) -> Vec<CubeMove> {
+    let mut turns: Vec<(Direction, u8)> = Direction::iter()
+        .map(|face| {
+            let turn = quarter_turns
+                .iter()
+                .filter(|(side, _)| *side == face)
+                .map(|(_, turn)| turn)
+                .sum::<u8>()
+                % 4;
+            (face, turn)
+        })
+        .collect();
+    let mut moves = Vec::new();
+    let up_index = turns
+        .iter()
+        .position(|(face, _)| *face == Direction::Up)
+        .unwrap();
+    for index in 0..turns.len() {
+        let face = turns[index].0.clone();
+        if face == Direction::Up {
+            continue;
+        }
+        // an odd turn is made even by a quarter turn shared with the U center
+        if turns[index].1 % 2 == 1 {
+            let (algorithm, partner) = if face == Direction::Down {
+                (OPPOSITE_QUARTER_TURNS, Direction::Down)
+            } else {
+                (ADJACENT_QUARTER_TURNS, Direction::Right)
+            };
+            if turns[index].1 == 1 {
+                // the face clockwise, U counterclockwise
+                moves.extend(moved(algorithm, &face, Some((&partner, &Direction::Up))));
+                turns[up_index].1 = (turns[up_index].1 + 1) % 4;
+            } else {
+                moves.extend(moved(algorithm, &Direction::Up, Some((&partner, &face))));
+                turns[up_index].1 = (turns[up_index].1 + 3) % 4;
+            }
+        } else if turns[index].1 == 2 {
+            moves.extend(moved(HALF_TURN, &face, None));
+        }
+    }
+    if turns[up_index].1 == 2 {
+        moves.extend(moved(HALF_TURN, &Direction::Up, None));
+    }
+    simplify(&moves)
+}
+
+// moves that turn the centers of a 3x3 supercube back the way they started without moving any other piece
+// the pieces have to be solved first, or at least have the corners in an even permutation
+pub fn solve_centers(cube: &Cube) -> Result<Vec<CubeMove>, SolveError> {
+    if cube.size() != 3 {
+        return Err(SolveError::UnsupportedCube(CubieError::UnsupportedSize(
+            cube.size(),
+        )));
+    }
+    let untwists: Vec<(Direction, u8)> = cube
+        .center_twists()
+        .into_iter()
+        .map(|(face, twist)| (face, (4 - twist) % 4))
+        .collect();
+    if untwists
+        .iter()
+        .map(|(_, twist)| *twist as usize)
+        .sum::<usize>()
+        % 2
+        == 1
+    {
+        return Err(SolveError::Unsolvable(vec![ValidationError::CenterParity]));
+    }
+    Ok(center_turns(&untwists))
+}
+
+// the cube as a supercube, for pieces read from stickers that can't show how the centers are turned
+// the centers stay the way they started, except that the U center is turned a quarter turn when the corners
+// are an odd permutation, since a real supercube never has every center upright then
+pub fn from_pieces(cube: Cube) -> Cube {
+    let mut supercube = cube.into_supercube();
+    let odd = CubieCube::relative_to_centers(&supercube)
+        .is_ok_and(|cubie| is_odd_permutation(&cubie.cp.map(|corner| corner as usize)));
+    if odd {
+        supercube.turn_center(&Direction::Up, 1);
+    }
+    supercube
+}
+
+// moves that turn the centers a random way a real supercube allows and leave the pieces alone
+pub fn random_center_turns(rng: &mut impl Rng) -> Vec<CubeMove> {
+    let mut turns: Vec<(Direction, u8)> = Direction::iter()
+        .map(|face| (face, rng.gen_range(0..4)))
+        .collect();
+    let total: u8 = turns.iter().map(|(_, turn)| turn).sum();
+    if total % 2 == 1 {
+        turns[0].1 = (turns[0].1 + 1) % 4;
+    }
+    center_turns(&turns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scramble::rng_from_seed;
+    use crate::validation;
+
+    fn played(cube: &mut Cube, moves: &[CubeMove]) {
+        for cube_move in moves {
+            cube.apply_move(cube_move);
+        }
+    }
+
+    #[test]
+    fn turns_the_centers_back() {
+        let mut rng = rng_from_seed(1);
+        for _ in 0..5 {
+            let mut cube = Cube::new().into_supercube();
+            played(&mut cube, &random_center_turns(&mut rng));
+            validation::validate(&cube).unwrap();
+            let moves = solve_centers(&cube).unwrap();
+            played(&mut cube, &moves);
+            assert!(cube.center_twists().iter().all(|(_, twist)| *twist == 0));
+            assert!(cube.is_solved());
+        }
+    }
+
+    #[test]
+    fn gives_pieces_from_facelets_centers_that_go_with_them() {
+        // the facelets after an R, which a supercube only reaches with a center a quarter turn off
+        let after_r = "UUFUUFUUFRRRRRRRRRFFDFFDFFDDDBDDBDDBLLLLLLLLLUBBUBBUBB";
+        let mut cube = from_pieces(Cube::from_facelets(after_r).unwrap());
+        validation::validate(&cube).unwrap();
+        assert!(cube.center_twists().contains(&(Direction::Up, 1)));
+        played(&mut cube, &[CubeMove::RPrime]);
+        let moves = solve_centers(&cube).unwrap();
+        played(&mut cube, &moves);
+        assert!(cube.is_solved());
+
+        let solved = from_pieces(Cube::new());
+        assert!(solved.center_twists().iter().all(|(_, twist)| *twist == 0));
+    }
+}
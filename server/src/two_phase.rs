@@ -0,0 +1,707 @@
+use crate::cube::{Cube, CubeMove, Metric};
+use crate::cubie::{Corner, CubieCube, CubieError, Edge, FACE_TURNS as MOVES};
+use crate::validation::{self, ValidationError};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use strum::IntoEnumIterator;
+
+// Kociemba's two-phase algorithm, finds short (though not always optimal) solutions for a 3x3 quickly
+// Phase 1 brings the cube into the group generated by U, D, R2, L2, F2 and B2: every corner and edge oriented
+// and the middle layer edges in the middle layer. Phase 2 then solves the cube with only those moves.
+// Both phases are IDA* searches over small coordinates of the cubie cube, guided by pruning tables
+// that give a lower bound for the number of moves left. The tables are built the first time the solver is used.
+
+const N_TWIST: usize = 2187; // 3^7 corner orientations, the last corner follows from the others
+const N_FLIP: usize = 2048; // 2^11 edge orientations
+const N_SLICE: usize = 495; // 12 choose 4 places for the middle layer edges
+const N_PERM_8: usize = 40320; // 8! orders of the corners, or of the Up and Down layer edges
+const N_SLICE_PERM: usize = 24; // 4! orders of the middle layer edges
+const N_MOVES: usize = 18;
+
+// the solver uses the face turns, move m turns face m / 3 by m % 3 + 1 quarter turns
+// the moves that keep the cube in the phase 2 group
+const PHASE_2_MOVES: [usize; 10] = [0, 1, 2, 4, 7, 9, 10, 11, 13, 16];
+
+// the cube is also searched turned so that each of the other two axes is upright, phase 1 of one
+// of them is often shorter than phase 1 of the cube as it is held
+const VIEW_ROTATIONS: [Option<CubeMove>; 3] = [None, Some(CubeMove::X), Some(CubeMove::Z)];
+
+// move tables give the coordinate after each move, pruning tables the distance to solved for a pair of coordinates
+struct Tables {
+    moves: Vec<CubieCube>,
+    view_moves: Vec<[usize; N_MOVES]>, // for each view rotation, the move of the cube itself that each move in the view is
+    twist_move: Vec<[u16; N_MOVES]>,
+    flip_move: Vec<[u16; N_MOVES]>,
+    slice_move: Vec<[u16; N_MOVES]>,
+    // phase 2 tables only hold the phase 2 moves
+    corner_perm_move: Vec<[u16; N_MOVES]>,
+    edge_perm_move: Vec<[u16; N_MOVES]>,
+    slice_perm_move: Vec<[u16; N_MOVES]>,
+    twist_slice_prune: Vec<u8>,
+    flip_slice_prune: Vec<u8>,
+    twist_flip_prune: Vec<u8>,
+    corner_slice_perm_prune: Vec<u8>,
+    edge_slice_perm_prune: Vec<u8>,
+}
+
+static TABLES: OnceLock<Tables> = OnceLock::new();
+
+fn tables() -> &'static Tables {
+    TABLES.get_or_init(|| {
+        let moves: Vec<CubieCube> = MOVES
+            .iter()
+            .map(|cube_move| CubieCube::from_move(cube_move).unwrap())
+            .collect();
+        let all_moves: Vec<usize> = (0..N_MOVES).collect();
+
+        let twist_move = move_table(&moves, &all_moves, N_TWIST, set_twist, twist);
+        let flip_move = move_table(&moves, &all_moves, N_FLIP, set_flip, flip);
+        let slice_move = move_table(&moves, &all_moves, N_SLICE, set_slice, slice);
+        let corner_perm_move = move_table(
+            &moves,
+            &PHASE_2_MOVES,
+            N_PERM_8,
+            set_corner_perm,
+            corner_perm,
+        );
+        let edge_perm_move = move_table(&moves, &PHASE_2_MOVES, N_PERM_8, set_edge_perm, edge_perm);
+        let slice_perm_move = move_table(
+            &moves,
+            &PHASE_2_MOVES,
+            N_SLICE_PERM,
+            set_slice_perm,
+            slice_perm,
+        );
+
+        let view_moves = VIEW_ROTATIONS
+            .iter()
+            .map(|rotation| {
+                let rotated_moves: Vec<CubieCube> =
+                    moves.iter().map(|m| rotated(m, rotation)).collect();
+                std::array::from_fn(|m| {
+                    rotated_moves
+                        .iter()
+                        .position(|rotated_move| *rotated_move == moves[m])
+                        .unwrap()
+                })
+            })
+            .collect();
+
+        Tables {
+            twist_slice_prune: pruning_table(&all_moves, &twist_move, &slice_move),
+            flip_slice_prune: pruning_table(&all_moves, &flip_move, &slice_move),
+            twist_flip_prune: pruning_table(&all_moves, &twist_move, &flip_move),
+            corner_slice_perm_prune: pruning_table(
+                &PHASE_2_MOVES,
+                &corner_perm_move,
+                &slice_perm_move,
+            ),
+            edge_slice_perm_prune: pruning_table(&PHASE_2_MOVES, &edge_perm_move, &slice_perm_move),
+            moves,
+            view_moves,
+            twist_move,
+            flip_move,
+            slice_move,
+            corner_perm_move,
+            edge_perm_move,
+            slice_perm_move,
+        }
+    })
+}
+
+// the coordinate after each move, found by building a cube for each coordinate and turning it
+fn move_table(
+    moves: &[CubieCube],
+    allowed_moves: &[usize],
+    size: usize,
+    set: impl Fn(usize) -> CubieCube,
+    get: impl Fn(&CubieCube) -> usize,
+) -> Vec<[u16; N_MOVES]> {
+    (0..size)
+        .map(|coordinate| {
+            let cubie = set(coordinate);
+            let mut row = [0; N_MOVES];
+            for &m in allowed_moves {
+                row[m] = get(&cubie.compose(&moves[m])) as u16;
+            }
+            row
+        })
+        .collect()
+}
+
+// breadth first search from solved over a pair of coordinates, solved is 0 in every coordinate
+fn pruning_table(
+    allowed_moves: &[usize],
+    first_move: &[[u16; N_MOVES]],
+    second_move: &[[u16; N_MOVES]],
+) -> Vec<u8> {
+    let n_second = second_move.len();
+    let mut table = vec![u8::MAX; first_move.len() * n_second];
+    let mut queue = VecDeque::from([0]);
+    table[0] = 0;
+    while let Some(index) = queue.pop_front() {
+        let (first, second) = (index / n_second, index % n_second);
+        for &m in allowed_moves {
+            let next = first_move[first][m] as usize * n_second + second_move[second][m] as usize;
+            if table[next] == u8::MAX {
+                table[next] = table[index] + 1;
+                queue.push_back(next);
+            }
+        }
+    }
+    table
+}
+
+// the cubie cube as seen after turning the whole cube
+fn rotated(cubie: &CubieCube, rotation: &Option<CubeMove>) -> CubieCube {
+    let mut cube = Cube::try_from(cubie).unwrap();
+    if let Some(rotation) = rotation {
+        cube.apply_move(rotation);
+    }
+    CubieCube::relative_to_centers(&cube).unwrap()
+}
+
+fn twist(cubie: &CubieCube) -> usize {
+    cubie.co[..7]
+        .iter()
+        .fold(0, |coordinate, &twist| coordinate * 3 + twist as usize)
+}
+
+fn set_twist(coordinate: usize) -> CubieCube {
+    let mut cubie = CubieCube::identity();
+    let mut rest = coordinate;
+    for i in (0..7).rev() {
+        cubie.co[i] = (rest % 3) as u8;
+        rest /= 3;
+    }
+    cubie.co[7] = (3 - cubie.co[..7].iter().sum::<u8>() % 3) % 3;
+    cubie
+}
+
+fn flip(cubie: &CubieCube) -> usize {
+    cubie.eo[..11]
+        .iter()
+        .fold(0, |coordinate, &flip| coordinate * 2 + flip as usize)
+}
+
+fn set_flip(coordinate: usize) -> CubieCube {
+    let mut cubie = CubieCube::identity();
+    let mut rest = coordinate;
+    for i in (0..11).rev() {
+        cubie.eo[i] = (rest % 2) as u8;
+        rest /= 2;
+    }
+    cubie.eo[11] = cubie.eo[..11].iter().sum::<u8>() % 2;
+    cubie
+}
+
+fn is_slice_edge(edge: Edge) -> bool {
+    edge as usize >= Edge::FR as usize
+}
+
+// which 4 of the 12 slots hold the middle layer edges, 0 when they are all home
+fn slice(cubie: &CubieCube) -> usize {
+    slice_from_slots(|slot| is_slice_edge(cubie.ep[slot]))
+}
+
+fn slice_from_slots(is_slice_slot: impl Fn(usize) -> bool) -> usize {
+    let mut coordinate = 0;
+    let mut found = 0;
+    for slot in (0..12).rev() {
+        if is_slice_slot(slot) {
+            found += 1;
+            coordinate += binomial(11 - slot, found);
+        }
+    }
+    coordinate
+}
+
+fn set_slice(coordinate: usize) -> CubieCube {
+    let mask = (0u16..1 << 12)
+        .filter(|mask| mask.count_ones() == 4)
+        .find(|mask| slice_from_slots(|slot| mask & (1 << slot) != 0) == coordinate)
+        .unwrap();
+    let mut slice_edges = Edge::iter().filter(|edge| is_slice_edge(*edge));
+    let mut other_edges = Edge::iter().filter(|edge| !is_slice_edge(*edge));
+    let mut cubie = CubieCube::identity();
+    for slot in 0..12 {
+        cubie.ep[slot] = if mask & (1 << slot) != 0 {
+            slice_edges.next().unwrap()
+        } else {
+            other_edges.next().unwrap()
+        };
+    }
+    cubie
+}
+
+fn corner_perm(cubie: &CubieCube) -> usize {
+    permutation_rank(&cubie.cp.map(|corner| corner as usize))
+}
+
+fn set_corner_perm(coordinate: usize) -> CubieCube {
+    let mut cubie = CubieCube::identity();
+    for (slot, corner) in permutation_unrank(coordinate, 8).into_iter().enumerate() {
+        cubie.cp[slot] = Corner::iter().nth(corner).unwrap();
+    }
+    cubie
+}
+
+// order of the Up and Down layer edges, only meaningful in phase 2 where they stay in those layers
+fn edge_perm(cubie: &CubieCube) -> usize {
+    permutation_rank(
+        &cubie.ep[..8]
+            .iter()
+            .map(|edge| *edge as usize)
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn set_edge_perm(coordinate: usize) -> CubieCube {
+    let mut cubie = CubieCube::identity();
+    for (slot, edge) in permutation_unrank(coordinate, 8).into_iter().enumerate() {
+        cubie.ep[slot] = Edge::iter().nth(edge).unwrap();
+    }
+    cubie
+}
+
+fn slice_perm(cubie: &CubieCube) -> usize {
+    permutation_rank(
+        &cubie.ep[8..]
+            .iter()
+            .map(|edge| *edge as usize - 8)
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn set_slice_perm(coordinate: usize) -> CubieCube {
+    let mut cubie = CubieCube::identity();
+    for (slot, edge) in permutation_unrank(coordinate, 4).into_iter().enumerate() {
+        cubie.ep[8 + slot] = Edge::iter().nth(8 + edge).unwrap();
+    }
+    cubie
+}
+
+fn binomial(n: usize, k: usize) -> usize {
+    if k > n {
+        return 0;
+    }
+    (0..k).fold(1, |result, i| result * (n - i) / (i + 1))
+}
+
+// index of a permutation of 0..n in lexicographic order, the identity is 0
+fn permutation_rank(permutation: &[usize]) -> usize {
+    let n = permutation.len();
+    (0..n).fold(0, |rank, i| {
+        let smaller_after = permutation[i + 1..]
+            .iter()
+            .filter(|&&later| later < permutation[i])
+            .count();
+        rank * (n - i) + smaller_after
+    })
+}
+
+fn permutation_unrank(rank: usize, n: usize) -> Vec<usize> {
+    let mut digits = vec![0; n];
+    let mut rest = rank;
+    for i in (0..n).rev() {
+        digits[i] = rest % (n - i);
+        rest /= n - i;
+    }
+    let mut unused: Vec<usize> = (0..n).collect();
+    digits
+        .into_iter()
+        .map(|digit| unused.remove(digit))
+        .collect()
+}
+
+// turning the same face twice in a row is never needed, and opposite faces are only turned in one order
+pub(crate) fn can_follow(previous: Option<&usize>, m: usize) -> bool {
+    match previous {
+        None => true,
+        Some(previous) => {
+            let (face, previous_face) = (m / 3, previous / 3);
+            face != previous_face && !(face % 3 == previous_face % 3 && face < previous_face)
+        }
+    }
+}
+
+// Tells a search when to stop, once a deadline passes or another thread sets a cancel flag
+// looking at either on every node would slow the search down, so they are only looked at every few thousand nodes
+pub(crate) struct StopCheck<'a> {
+    deadline: Option<Instant>,
+    cancel: Option<&'a AtomicBool>,
+    pub(crate) nodes: u64,    // nodes the search has been on so far
+    pub(crate) stopped: bool, // stays set once the search has to stop
+}
+
+impl<'a> StopCheck<'a> {
+    pub(crate) fn new(deadline: Option<Instant>, cancel: Option<&'a AtomicBool>) -> Self {
+        StopCheck {
+            deadline,
+            cancel,
+            nodes: 0,
+            stopped: false,
+        }
+    }
+
+    // counts one more node, true once the search has to stop
+    pub(crate) fn should_stop(&mut self) -> bool {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(4096)
+            && (self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+                || self
+                    .cancel
+                    .is_some_and(|cancel| cancel.load(Ordering::Relaxed)))
+        {
+            self.stopped = true;
+        }
+        self.stopped
+    }
+}
+
+// a way of looking at the cube to solve: turned by one of the view rotations, and maybe inverted,
+// the moves that solve an inverted cube solve the cube itself when played backwards
+struct View {
+    cubie: CubieCube,
+    moves: &'static [usize; N_MOVES], // the move of the cube itself that each move in the view is
+    inverse: bool,
+}
+
+impl View {
+    fn solution(&self, moves: &[usize]) -> Vec<usize> {
+        let moves = moves.iter().map(|&m| self.moves[m]);
+        if self.inverse {
+            moves.rev().map(|m| m / 3 * 3 + 2 - m % 3).collect()
+        } else {
+            moves.collect()
+        }
+    }
+}
+
+struct Search {
+    tables: &'static Tables,
+    views: Vec<View>,
+    view: usize,             // the one being searched
+    costs: [usize; N_MOVES], // what each move counts as in the metric being optimized
+    moves: Vec<usize>,
+    best: Option<Vec<usize>>,
+    best_length: usize, // of the best solution so far, or one more than the longest allowed before there is one
+    stop_at_first: bool,
+    stop: StopCheck<'static>,
+}
+
+impl Search {
+    fn done(&self) -> bool {
+        self.stop.stopped || (self.stop_at_first && self.best.is_some())
+    }
+
+    fn length(&self) -> usize {
+        self.moves.iter().map(|&m| self.costs[m]).sum()
+    }
+
+    // every phase 1 solution of exactly togo moves is tried with the phase 2 moves that are left
+    // togo and the solution lengths are counted in the metric, the pruning tables count face turns
+    // which is never more than any metric counts, so they still give a lower bound
+    fn phase_1(&mut self, twist: usize, flip: usize, slice: usize, togo: usize) {
+        if self.stop.should_stop() {
+            return;
+        }
+        if togo == 0 {
+            // a solution ending in a phase 2 move was already tried one move earlier
+            let ends_in_phase_2_move = self.moves.last().is_some_and(|m| PHASE_2_MOVES.contains(m));
+            if twist == 0 && flip == 0 && slice == 0 && !ends_in_phase_2_move {
+                self.start_phase_2();
+            }
+            return;
+        }
+        let tables = self.tables;
+        for m in 0..N_MOVES {
+            let cost = self.costs[m];
+            if !can_follow(self.moves.last(), m) || cost > togo {
+                continue;
+            }
+            let twist = tables.twist_move[twist][m] as usize;
+            let flip = tables.flip_move[flip][m] as usize;
+            let slice = tables.slice_move[slice][m] as usize;
+            // each table is only looked at when the ones before it didn't already rule the move out
+            let too_far = |distance: u8| distance as usize > togo - cost;
+            if too_far(tables.twist_slice_prune[twist * N_SLICE + slice])
+                || too_far(tables.flip_slice_prune[flip * N_SLICE + slice])
+                || too_far(tables.twist_flip_prune[twist * N_FLIP + flip])
+            {
+                continue;
+            }
+            self.moves.push(m);
+            self.phase_1(twist, flip, slice, togo - cost);
+            self.moves.pop();
+            if self.done() {
+                return;
+            }
+        }
+    }
+
+    // phase 2 only has to beat the best solution so far, so it never searches deeper than what that leaves
+    fn start_phase_2(&mut self) {
+        let phase_1_length = self.length();
+        let Some(left) = (self.best_length - 1).checked_sub(phase_1_length) else {
+            return;
+        };
+        let cubie = self
+            .moves
+            .iter()
+            .fold(self.views[self.view].cubie.clone(), |cubie, &m| {
+                cubie.compose(&self.tables.moves[m])
+            });
+        let (corners, edges, slice) = (corner_perm(&cubie), edge_perm(&cubie), slice_perm(&cubie));
+        let phase_1_moves = self.moves.len();
+        for togo in self.phase_2_distance(corners, edges, slice)..=left {
+            if self.phase_2(corners, edges, slice, togo) {
+                self.best_length = self.length();
+                self.best = Some(self.views[self.view].solution(&self.moves));
+                self.moves.truncate(phase_1_moves);
+                return;
+            }
+            if self.stop.stopped {
+                return;
+            }
+        }
+    }
+
+    fn phase_2_distance(&self, corners: usize, edges: usize, slice: usize) -> usize {
+        let tables = self.tables;
+        tables.corner_slice_perm_prune[corners * N_SLICE_PERM + slice]
+            .max(tables.edge_slice_perm_prune[edges * N_SLICE_PERM + slice]) as usize
+    }
+
+    // leaves the moves that solve the cube on self.moves when it finds them
+    fn phase_2(&mut self, corners: usize, edges: usize, slice: usize, togo: usize) -> bool {
+        if self.stop.should_stop() {
+            return false;
+        }
+        if togo == 0 {
+            return corners == 0 && edges == 0 && slice == 0;
+        }
+        let tables = self.tables;
+        for &m in PHASE_2_MOVES.iter() {
+            let cost = self.costs[m];
+            if !can_follow(self.moves.last(), m) || cost > togo {
+                continue;
+            }
+            let corners = tables.corner_perm_move[corners][m] as usize;
+            let edges = tables.edge_perm_move[edges][m] as usize;
+            let slice = tables.slice_perm_move[slice][m] as usize;
+            if self.phase_2_distance(corners, edges, slice) > togo - cost {
+                continue;
+            }
+            self.moves.push(m);
+            if self.phase_2(corners, edges, slice, togo - cost) {
+                return true;
+            }
+            self.moves.pop();
+        }
+        false
+    }
+}
+
+// Reasons the solver didn't return a solution
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum SolveError {
+    UnsupportedCube(CubieError),      // the solver can't take this size
+    Unsolvable(Vec<ValidationError>), // no real cube can be in this state
+    NotFound,                         // no solution within the move limit was found in time
+    PatternCentersUnreachable,        // the pattern moves centers, which face turns never do
+    Cancelled,                        // the search was stopped before it found a solution
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SolveError::UnsupportedCube(CubieError::UnsupportedSize(size)) => {
+                write!(f, "a {}x{} cube can't be solved this way", size, size)
+            }
+            SolveError::UnsupportedCube(CubieError::CentersMoved) => {
+                write!(f, "the centers have to be in place to solve the cube")
+            }
+            SolveError::UnsupportedCube(CubieError::InvalidPieces) => {
+                write!(f, "every piece has to be in exactly one slot")
+            }
+            SolveError::Unsolvable(errors) => {
+                let errors: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+                write!(f, "the cube can't be solved: {}", errors.join(", "))
+            }
+            SolveError::NotFound => {
+                write!(f, "no solution within the move limit was found in time")
+            }
+            SolveError::PatternCentersUnreachable => {
+                write!(
+                    f,
+                    "face turns can't move the centers where the pattern has them"
+                )
+            }
+            SolveError::Cancelled => write!(f, "the search was cancelled"),
+        }
+    }
+}
+
+// solve a 3x3 in at most max_length moves counted in the metric, from its centers so it works however the cube is held
+// without a timeout this returns the first solution found, which is usually well under a second for a max_length of 20 or more
+// but can take very long below that, with a timeout it keeps looking for shorter solutions until time
+// runs out and returns the shortest one found
+pub fn solve(
+    cube: &Cube,
+    max_length: usize,
+    metric: Metric,
+    timeout: Option<Duration>,
+) -> Result<Vec<CubeMove>, SolveError> {
+    validation::validate(cube).map_err(SolveError::Unsolvable)?;
+    let cubie = CubieCube::relative_to_centers(cube).map_err(SolveError::UnsupportedCube)?;
+
+    // building the tables doesn't count against the timeout
+    tables();
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    solve_cubie(&cubie, max_length, metric, deadline).ok_or(SolveError::NotFound)
+}
+
+// moves that solve the cubie cube, at most max_length of them in the metric
+// without a deadline the first solution found, with one the shortest found before it passes
+// phase 1 goes one move deeper at a time, every solution found bounds the phase 2 searches after it,
+// and the search ends early once phase 1 alone is as long as the best solution
+pub(crate) fn solve_cubie(
+    cubie: &CubieCube,
+    max_length: usize,
+    metric: Metric,
+    deadline: Option<Instant>,
+) -> Option<Vec<CubeMove>> {
+    let tables = tables();
+    let views: Vec<View> = [false, true]
+        .into_iter()
+        .flat_map(|inverse| {
+            let cubie = if inverse {
+                cubie.inverse()
+            } else {
+                cubie.clone()
+            };
+            VIEW_ROTATIONS
+                .iter()
+                .zip(&tables.view_moves)
+                .map(move |(rotation, moves)| View {
+                    cubie: rotated(&cubie, rotation),
+                    moves,
+                    inverse,
+                })
+        })
+        .collect();
+    let mut search = Search {
+        tables,
+        views,
+        view: 0,
+        costs: MOVES.each_ref().map(|m| m.count(metric, 3)),
+        moves: Vec::new(),
+        best: None,
+        best_length: max_length + 1,
+        stop_at_first: deadline.is_none(),
+        stop: StopCheck::new(deadline, None),
+    };
+    // every view is searched one phase 1 move deeper before any goes deeper still
+    for togo in 0..=max_length {
+        for view in 0..search.views.len() {
+            if togo >= search.best_length || search.done() {
+                break;
+            }
+            let cubie = &search.views[view].cubie;
+            let (twist, flip, slice) = (twist(cubie), flip(cubie), slice(cubie));
+            search.view = view;
+            search.phase_1(twist, flip, slice, togo);
+        }
+    }
+    search
+        .best
+        .map(|moves| moves.iter().map(|&m| MOVES[m].clone()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cube;
+    use crate::scramble::{random_state, rng_from_seed};
+
+    fn solves(cubie: &CubieCube, moves: &[CubeMove]) -> bool {
+        cubie
+            .compose(&CubieCube::from_moves(moves).unwrap())
+            .is_identity()
+    }
+
+    #[test]
+    fn solves_random_states_in_at_most_21_moves() {
+        let mut rng = rng_from_seed(1);
+        for _ in 0..5 {
+            let state = random_state(&mut rng);
+            let moves = solve_cubie(&state, 21, Metric::Htm, None).unwrap();
+            assert!(solves(&state, &moves));
+            assert!(moves.len() <= 21);
+        }
+    }
+
+    #[test]
+    fn keeps_looking_for_shorter_solutions_until_the_deadline() {
+        let mut rng = rng_from_seed(2);
+        let state = random_state(&mut rng);
+        let first = solve_cubie(&state, 30, Metric::Htm, None).unwrap();
+        let deadline = Instant::now() + Duration::from_millis(500);
+        let shortest = solve_cubie(&state, 30, Metric::Htm, Some(deadline)).unwrap();
+        assert!(solves(&state, &shortest));
+        assert!(shortest.len() <= first.len());
+    }
+
+    #[test]
+    fn counts_moves_in_the_metric() {
+        let mut rng = rng_from_seed(3);
+        let state = random_state(&mut rng);
+        let moves = solve_cubie(&state, 34, Metric::Qtm, None).unwrap();
+        assert!(solves(&state, &moves));
+        assert!(cube::length(&moves, Metric::Qtm, 3) <= 34);
+    }
+
+    #[test]
+    fn solves_a_cube_held_another_way() {
+        let mut cube = Cube::new();
+        for cube_move in [
+            CubeMove::R,
+            CubeMove::U,
+            CubeMove::X,
+            CubeMove::FPrime,
+            CubeMove::M,
+        ] {
+            cube.apply_move(&cube_move);
+        }
+        let moves = solve(&cube, 30, Metric::Htm, None).unwrap();
+        for cube_move in moves.iter() {
+            cube.apply_move(cube_move);
+        }
+        assert!(cube.is_solved_up_to_rotation());
+    }
+
+    #[test]
+    fn rejects_cubes_it_cant_solve() {
+        assert_eq!(
+            solve(&Cube::with_size(2), 30, Metric::Htm, None),
+            Err(SolveError::UnsupportedCube(CubieError::UnsupportedSize(2)))
+        );
+        // a D sticker in place of a U one makes a corner that doesn't exist
+        let mut facelets: Vec<char> = "UUUUUUUUURRRRRRRRRFFFFFFFFFDDDDDDDDDLLLLLLLLLBBBBBBBBB"
+            .chars()
+            .collect();
+        facelets[8] = 'D';
+        let cube = Cube::from_facelets(&facelets.iter().collect::<String>()).unwrap();
+        assert!(matches!(
+            solve(&cube, 30, Metric::Htm, None),
+            Err(SolveError::Unsolvable(_))
+        ));
+    }
+}
@@ -0,0 +1,358 @@
+use crate::cube::{Cube, Direction, StickerColor};
+use crate::cubie::{is_odd_permutation, Corner, Edge};
+use serde::Serialize;
+use std::fmt;
+use strum::IntoEnumIterator;
+
+// Checks that a cube state can be reached by turning a solved cube
+// States built from user input, like facelet strings, can easily show stickers no real cube could
+// Pieces are named after the faces whose centers have their colors, the same way facelet strings are written
+// Sticker counts, centers, pieces and corner twist are checked on every size,
+// edge flip and permutation parity only on a 3x3 since the edges of bigger cubes follow other rules,
+// and on a 3x3 supercube also whether the centers are turned in a way that goes with the pieces
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Twist {
+    Clockwise,
+    CounterClockwise,
+}
+
+// A reason the cube can't be solved, each one readable on its own through Display
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum ValidationError {
+    StickerCount {
+        color: StickerColor,
+        count: usize,
+        expected: usize,
+    },
+    CentersMismatched, // the centers are in an order no real cube has, for example Up and Front swapped
+    NonexistentCorner {
+        colors: Vec<StickerColor>,
+    }, // also mirror images of real corners
+    NonexistentEdge {
+        colors: Vec<StickerColor>,
+    },
+    CornerCount {
+        corner: Corner,
+        count: usize,
+    },
+    EdgeCount {
+        edge: Edge,
+        count: usize,
+        expected: usize,
+    },
+    CornerTwisted {
+        corner: Corner,
+        twist: Twist,
+    }, // the twists of all corners must add up to whole turns
+    EdgeFlipped {
+        edge: Edge,
+    }, // edges can only be flipped in pairs
+    PermutationParity, // as if two pieces had been swapped
+    CenterParity,      // on a supercube, as if a single center had been turned a quarter turn
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationError::StickerCount {
+                color,
+                count,
+                expected,
+            } => write!(
+                f,
+                "{:?} appears on {} stickers instead of {}",
+                color, count, expected
+            ),
+            ValidationError::CentersMismatched => {
+                write!(f, "centers are not arranged like on a real cube")
+            }
+            ValidationError::NonexistentCorner { colors } => {
+                write!(f, "there is no corner with colors {:?}", colors)
+            }
+            ValidationError::NonexistentEdge { colors } => {
+                write!(f, "there is no edge with colors {:?}", colors)
+            }
+            ValidationError::CornerCount { corner, count: 0 } => {
+                write!(f, "corner {:?} is missing", corner)
+            }
+            ValidationError::CornerCount { corner, count } => {
+                write!(f, "corner {:?} appears {} times", corner, count)
+            }
+            ValidationError::EdgeCount { edge, count: 0, .. } => {
+                write!(f, "edge {:?} is missing", edge)
+            }
+            ValidationError::EdgeCount {
+                edge,
+                count,
+                expected,
+            } => write!(
+                f,
+                "edge {:?} appears {} times instead of {}",
+                edge, count, expected
+            ),
+            ValidationError::CornerTwisted {
+                corner,
+                twist: Twist::Clockwise,
+            } => write!(f, "corner {:?} twisted clockwise", corner),
+            ValidationError::CornerTwisted {
+                corner,
+                twist: Twist::CounterClockwise,
+            } => write!(f, "corner {:?} twisted counterclockwise", corner),
+            ValidationError::EdgeFlipped { edge } => write!(f, "edge {:?} flipped", edge),
+            ValidationError::PermutationParity => write!(f, "two pieces are swapped"),
+            ValidationError::CenterParity => write!(f, "a center is turned a quarter turn"),
+        }
+    }
+}
+
+// every problem found with the cube
+// piece level checks only run once the stickers add up, otherwise they would mostly repeat the same problem
+pub fn validate(cube: &Cube) -> Result<(), Vec<ValidationError>> {
+    let mut errors = check_stickers(cube);
+    if errors.is_empty() {
+        errors = check_pieces(cube);
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn check_stickers(cube: &Cube) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    let expected = cube.size() * cube.size();
+    for direction in Direction::iter() {
+        let count = cube
+            .pieces
+            .iter()
+            .flat_map(|piece| piece.get_stickers())
+            .filter(|(_, initial_side)| *initial_side == direction)
+            .count();
+        if count != expected {
+            errors.push(ValidationError::StickerCount {
+                color: cube.color_scheme().color_of(&direction),
+                count,
+                expected,
+            });
+        }
+    }
+    if !cube.center_sides().is_rotation() {
+        errors.push(ValidationError::CentersMismatched);
+    }
+    errors
+}
+
+fn check_pieces(cube: &Cube) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    let center_sides = cube.center_sides();
+    // slot, piece and twist or flip of every corner and edge
+    let mut corners: Vec<(Corner, Corner, usize)> = Vec::new();
+    let mut edges: Vec<(Edge, Edge, usize)> = Vec::new();
+
+    for piece in cube.pieces.iter() {
+        let stickers = piece.get_stickers();
+        let colors = || {
+            stickers
+                .iter()
+                .map(|(_, initial_side)| cube.color_scheme().color_of(initial_side))
+                .collect()
+        };
+        // the face the sticker on each side of the slot belongs to, in the order the slot lists its sides
+        let faces_on = |sides: &[Direction]| -> Vec<Direction> {
+            sides
+                .iter()
+                .filter_map(|side| {
+                    let (_, initial_side) = stickers.iter().find(|(s, _)| s == side)?;
+                    Some(center_sides.face_of(initial_side))
+                })
+                .collect()
+        };
+
+        if stickers.len() == 3 {
+            let slot = Corner::iter().find(|slot| faces_on(&slot.faces()).len() == 3);
+            let found = slot.and_then(|slot| {
+                let faces = faces_on(&slot.faces());
+                (0..3).find_map(|twist| {
+                    Corner::iter()
+                        .find(|corner| (0..3).all(|k| corner.faces()[k] == faces[(k + twist) % 3]))
+                        .map(|corner| (slot, corner, twist))
+                })
+            });
+            match found {
+                Some(corner) => corners.push(corner),
+                None => errors.push(ValidationError::NonexistentCorner { colors: colors() }),
+            }
+        } else if stickers.len() == 2 {
+            let slot = Edge::iter().find(|slot| faces_on(&slot.faces()).len() == 2);
+            let found = slot.and_then(|slot| {
+                let faces = faces_on(&slot.faces());
+                Edge::iter().find_map(|edge| {
+                    let edge_faces = edge.faces();
+                    if edge_faces[..] == faces[..] {
+                        Some((slot, edge, 0))
+                    } else if edge_faces[0] == faces[1] && edge_faces[1] == faces[0] {
+                        Some((slot, edge, 1))
+                    } else {
+                        None
+                    }
+                })
+            });
+            match found {
+                Some(edge) => edges.push(edge),
+                None => errors.push(ValidationError::NonexistentEdge { colors: colors() }),
+            }
+        }
+    }
+
+    for corner in Corner::iter() {
+        let count = corners.iter().filter(|(_, c, _)| *c == corner).count();
+        if count != 1 {
+            errors.push(ValidationError::CornerCount { corner, count });
+        }
+    }
+    // every edge of a bigger cube is made of several pieces with the same colors
+    let expected = cube.size().saturating_sub(2);
+    for edge in Edge::iter() {
+        let count = edges.iter().filter(|(_, e, _)| *e == edge).count();
+        if count != expected {
+            errors.push(ValidationError::EdgeCount {
+                edge,
+                count,
+                expected,
+            });
+        }
+    }
+    if !errors.is_empty() {
+        return errors;
+    }
+
+    // the twist sum says which way a single corner would have to be turned back, name one that is twisted that way
+    let twist = corners.iter().map(|(_, _, twist)| twist).sum::<usize>() % 3;
+    if twist != 0 {
+        let (_, corner, _) = corners
+            .iter()
+            .find(|(_, _, t)| *t == twist)
+            .unwrap_or(&corners[0]);
+        errors.push(ValidationError::CornerTwisted {
+            corner: *corner,
+            twist: if twist == 1 {
+                Twist::Clockwise
+            } else {
+                Twist::CounterClockwise
+            },
+        });
+    }
+
+    if cube.size() == 3 {
+        let flip = edges.iter().map(|(_, _, flip)| flip).sum::<usize>() % 2;
+        if flip != 0 {
+            let (_, edge, _) = edges.iter().find(|(_, _, f)| *f == 1).unwrap_or(&edges[0]);
+            errors.push(ValidationError::EdgeFlipped { edge: *edge });
+        }
+
+        // every turn swaps corners and edges the same number of times, so both permutations are even or both odd
+        let mut corner_permutation = [0; 8];
+        for (slot, corner, _) in corners.iter() {
+            corner_permutation[*slot as usize] = *corner as usize;
+        }
+        let mut edge_permutation = [0; 12];
+        for (slot, edge, _) in edges.iter() {
+            edge_permutation[*slot as usize] = *edge as usize;
+        }
+        if is_odd_permutation(&corner_permutation) != is_odd_permutation(&edge_permutation) {
+            errors.push(ValidationError::PermutationParity);
+        }
+
+        // the same turns also turn the centers, an odd number of quarter turns in all for an odd permutation
+        if cube.is_supercube() {
+            let center_turns: u8 = cube.center_twists().iter().map(|(_, twist)| twist).sum();
+            if (center_turns % 2 == 1) != is_odd_permutation(&corner_permutation) {
+                errors.push(ValidationError::CenterParity);
+            }
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOLVED: &str = "UUUUUUUUURRRRRRRRRFFFFFFFFFDDDDDDDDDLLLLLLLLLBBBBBBBBB";
+
+    // the solved 3x3 with some stickers changed, by their index in the facelet string
+    fn errors_with(changes: &[(usize, char)]) -> Vec<ValidationError> {
+        let mut facelets: Vec<char> = SOLVED.chars().collect();
+        for (index, letter) in changes {
+            facelets[*index] = *letter;
+        }
+        let cube = Cube::from_facelets(&facelets.iter().collect::<String>()).unwrap();
+        validate(&cube).err().unwrap_or_default()
+    }
+
+    #[test]
+    fn accepts_reachable_states() {
+        assert!(validate(&Cube::from_facelets(SOLVED).unwrap()).is_ok());
+        let mut cube = Cube::new();
+        for cube_move in crate::notation::parse("R U F' M2 y Rw D'").unwrap() {
+            cube.apply_move(&cube_move);
+        }
+        assert!(validate(&cube).is_ok());
+    }
+
+    #[test]
+    fn finds_a_flipped_edge() {
+        // the U and F stickers of the UF edge swapped
+        assert_eq!(
+            errors_with(&[(7, 'F'), (19, 'U')]),
+            vec![ValidationError::EdgeFlipped { edge: Edge::UF }]
+        );
+    }
+
+    #[test]
+    fn finds_a_twisted_corner() {
+        let errors = errors_with(&[(8, 'F'), (9, 'U'), (20, 'R')]);
+        assert!(
+            matches!(
+                errors[..],
+                [ValidationError::CornerTwisted {
+                    corner: Corner::URF,
+                    ..
+                }]
+            ),
+            "{:?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn finds_two_swapped_pieces() {
+        // the F sticker of UF and the R sticker of UR swapped, which swaps the two edges
+        assert_eq!(
+            errors_with(&[(19, 'R'), (10, 'F')]),
+            vec![ValidationError::PermutationParity]
+        );
+    }
+
+    #[test]
+    fn counts_stickers_before_looking_at_pieces() {
+        assert_eq!(
+            errors_with(&[(0, 'R')]),
+            vec![
+                ValidationError::StickerCount {
+                    color: StickerColor::White,
+                    count: 8,
+                    expected: 9
+                },
+                ValidationError::StickerCount {
+                    color: StickerColor::Red,
+                    count: 10,
+                    expected: 9
+                }
+            ]
+        );
+    }
+}